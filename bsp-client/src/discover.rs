@@ -0,0 +1,100 @@
+use bsp_types::BspConnectionDetails;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directories searched for connection files, in precedence order: the workspace's `.bsp`
+/// directory, then the user directory, then the system directories.
+pub fn connection_dirs(workspace_root: impl AsRef<Path>) -> Vec<PathBuf> {
+    let mut dirs = vec![workspace_root.as_ref().join(".bsp")];
+    dirs.extend(user_dir());
+    dirs.extend(system_dirs());
+    dirs
+}
+
+/// List the build servers that can be launched for `workspace_root`, in precedence order.
+///
+/// Connection files that fail to parse are skipped. When several files declare the same server
+/// name, only the one with the highest precedence is kept.
+pub fn discover(workspace_root: impl AsRef<Path>) -> Vec<(PathBuf, BspConnectionDetails)> {
+    discover_in(&connection_dirs(workspace_root))
+}
+
+pub(crate) fn discover_in(dirs: &[PathBuf]) -> Vec<(PathBuf, BspConnectionDetails)> {
+    let mut found: Vec<(PathBuf, BspConnectionDetails)> = vec![];
+    for dir in dirs {
+        let mut paths: Vec<_> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect(),
+            Err(_) => continue,
+        };
+        paths.sort();
+
+        for path in paths {
+            let details = match read_details(&path) {
+                Ok(details) => details,
+                Err(err) => {
+                    tracing::warn!("skipping connection file {}: {}", path.display(), err);
+                    continue;
+                }
+            };
+            if !found.iter().any(|(_, d)| d.name() == details.name()) {
+                found.push((path, details));
+            }
+        }
+    }
+    found
+}
+
+fn read_details(path: &Path) -> Result<BspConnectionDetails, Box<dyn std::error::Error>> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+fn env_path(key: &str) -> Option<PathBuf> {
+    env::var_os(key)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn user_dir() -> Option<PathBuf> {
+    Some(env_path("HOME")?.join("Library/Application Support/bsp"))
+}
+
+#[cfg(target_os = "macos")]
+fn system_dirs() -> Vec<PathBuf> {
+    vec![PathBuf::from("/Library/Application Support/bsp")]
+}
+
+#[cfg(windows)]
+fn user_dir() -> Option<PathBuf> {
+    Some(env_path("LOCALAPPDATA")?.join("bsp"))
+}
+
+#[cfg(windows)]
+fn system_dirs() -> Vec<PathBuf> {
+    env_path("PROGRAMDATA")
+        .map(|dir| dir.join("bsp"))
+        .into_iter()
+        .collect()
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn user_dir() -> Option<PathBuf> {
+    let data_home =
+        env_path("XDG_DATA_HOME").or_else(|| Some(env_path("HOME")?.join(".local/share")));
+    Some(data_home?.join("bsp"))
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn system_dirs() -> Vec<PathBuf> {
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    env::split_paths(&data_dirs)
+        .map(|dir| dir.join("bsp"))
+        .collect()
+}
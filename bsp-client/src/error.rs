@@ -0,0 +1,42 @@
+use bsp_server::{InvalidMessage, ResponseError};
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The server answered the request with an error.
+    Response(ResponseError),
+    /// The server's result could not be deserialized.
+    Json(serde_json::Error),
+    /// The server's response could not be decoded.
+    Invalid(InvalidMessage),
+    /// The server did not answer before the configured timeout.
+    Timeout(String),
+    /// The connection to the server was closed.
+    Disconnected,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Response(err) => {
+                write!(
+                    f,
+                    "server responded with error {}: {}",
+                    err.code, err.message
+                )
+            }
+            ClientError::Json(err) => write!(f, "invalid response from server: {err}"),
+            ClientError::Invalid(invalid) => write!(f, "{invalid}"),
+            ClientError::Timeout(method) => write!(f, "server did not answer '{method}' in time"),
+            ClientError::Disconnected => f.write_str("connection to server closed"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
@@ -0,0 +1,316 @@
+//! A blocking build server client, reusing the connection and messages of `bsp-server`.
+//!
+//! [`Client::initialize`] performs the `build/initialize` → `build/initialized` handshake, after
+//! which every request is available as a typed method. Notifications sent by the server while
+//! waiting for a response are queued and can be read through [`Client::notifications`].
+//!
+//! Run with `RUST_LOG=bsp_client=trace` to see all the messages.
+mod discover;
+mod error;
+#[cfg(test)]
+mod tests;
+
+pub use bsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+pub use bsp_types as types;
+pub use discover::{connection_dirs, discover};
+pub use error::ClientError;
+
+use bsp_server::Outgoing;
+use bsp_types::*;
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// How long dropping a client waits for the server to acknowledge `build/shutdown`.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Client connected to a build server.
+pub struct Client {
+    conn: Connection,
+    outgoing: Outgoing<&'static str>,
+    server: InitializeBuildResult,
+    notifications: (Sender<Notification>, Receiver<Notification>),
+    is_initialized: bool,
+    is_shutdown: bool,
+}
+
+impl Client {
+    /// Initialize the server over `conn`, returning a client once the server accepted `params`
+    /// and was sent `build/initialized`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use bsp_client::{types::*, Client, Connection};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let (conn, _io_threads) = Connection::connect("127.0.0.1:9000")?;
+    ///     let root = Url::from_directory_path("/tmp/workspace").unwrap();
+    ///     let params = InitializeBuild::new_simple("MyClient", "0.1", "2.0", root, Default::default());
+    ///
+    ///     let mut client = Client::initialize(conn, params)?;
+    ///     for target in client.workspace_build_targets()?.targets {
+    ///         println!("{:?}", target.id);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn initialize(conn: Connection, params: InitializeBuild) -> Result<Self, ClientError> {
+        let mut client = Self {
+            conn,
+            outgoing: Outgoing::default(),
+            server: InitializeBuildResult::default(),
+            notifications: unbounded(),
+            is_initialized: false,
+            is_shutdown: false,
+        };
+        client.server = client.request(|id| Request::InitializeBuild(id, params))?;
+        match client.server.bsp_version().parse() {
+            Ok(version) => client.conn.set_bsp_version(version),
+            Err(err) => tracing::warn!("server talks an unknown BSP version: {}", err),
+        }
+        client.notify(Notification::Initialized)?;
+        client.is_initialized = true;
+        Ok(client)
+    }
+
+    /// The result the server answered `build/initialize` with.
+    pub fn server(&self) -> &InitializeBuildResult {
+        &self.server
+    }
+
+    /// Notifications received from the server.
+    pub fn notifications(&self) -> &Receiver<Notification> {
+        &self.notifications.1
+    }
+
+    /// Set how long requests may wait for the server to answer. `None` (the default) waits
+    /// forever. Requests left unanswered are cancelled with `$/cancelRequest`.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.outgoing.set_timeout(timeout);
+    }
+
+    /// Send "workspace/buildTargets"
+    pub fn workspace_build_targets(&mut self) -> Result<WorkspaceBuildTargetsResult, ClientError> {
+        self.request(Request::WorkspaceBuildTargets)
+    }
+
+    /// Send "workspace/reload"
+    pub fn workspace_reload(&mut self) -> Result<Value, ClientError> {
+        self.request(Request::WorkspaceReload)
+    }
+
+    /// Send "buildTarget/sources"
+    pub fn sources(
+        &mut self,
+        params: BuildTargetSources,
+    ) -> Result<BuildTargetSourcesResult, ClientError> {
+        self.request(|id| Request::BuildTargetSources(id, params))
+    }
+
+    /// Send "textDocument/inverseSources"
+    pub fn inverse_sources(
+        &mut self,
+        params: TextDocumentInverseSources,
+    ) -> Result<BuildTargetInverseSourcesResult, ClientError> {
+        self.request(|id| Request::TextDocumentInverseSources(id, params))
+    }
+
+    /// Send "buildTarget/dependencySources"
+    pub fn dependency_sources(
+        &mut self,
+        params: BuildTargetDependencySources,
+    ) -> Result<BuildTargetDependencySourcesResult, ClientError> {
+        self.request(|id| Request::BuildTargetDependencySources(id, params))
+    }
+
+    /// Send "buildTarget/dependencyModules"
+    pub fn dependency_modules(
+        &mut self,
+        params: BuildTargetDependencyModules,
+    ) -> Result<BuildTargetDependencyModulesResult, ClientError> {
+        self.request(|id| Request::BuildTargetDependencyModules(id, params))
+    }
+
+    /// Send "buildTarget/resources"
+    pub fn resources(
+        &mut self,
+        params: BuildTargetResources,
+    ) -> Result<BuildTargetResourcesResult, ClientError> {
+        self.request(|id| Request::BuildTargetResources(id, params))
+    }
+
+    /// Send "buildTarget/compile"
+    pub fn compile(
+        &mut self,
+        params: BuildTargetCompile,
+    ) -> Result<BuildTargetCompileResult, ClientError> {
+        self.request(|id| Request::BuildTargetCompile(id, params))
+    }
+
+    /// Send "buildTarget/test"
+    pub fn test(&mut self, params: BuildTargetTest) -> Result<BuildTargetTestResult, ClientError> {
+        self.request(|id| Request::BuildTargetTest(id, params))
+    }
+
+    /// Send "buildTarget/run"
+    pub fn run(&mut self, params: BuildTargetRun) -> Result<BuildTargetRunResult, ClientError> {
+        self.request(|id| Request::BuildTargetRun(id, params))
+    }
+
+    /// Send "buildTarget/cleanCache"
+    pub fn clean_cache(
+        &mut self,
+        params: BuildTargetCleanCache,
+    ) -> Result<BuildTargetCleanCacheResult, ClientError> {
+        self.request(|id| Request::BuildTargetCleanCache(id, params))
+    }
+
+    /// Send "buildTarget/jvmRunEnvironment"
+    pub fn jvm_run_environment(
+        &mut self,
+        params: JvmRunEnvironment,
+    ) -> Result<JvmRunEnvironmentResult, ClientError> {
+        self.request(|id| Request::BuildTargetJvmRunEnvironment(id, params))
+    }
+
+    /// Send "buildTarget/jvmTestEnvironment"
+    pub fn jvm_test_environment(
+        &mut self,
+        params: JvmTestEnvironment,
+    ) -> Result<JvmTestEnvironmentResult, ClientError> {
+        self.request(|id| Request::BuildTargetJvmTestEnvironment(id, params))
+    }
+
+    /// Send "buildTarget/jvmCompileClasspath"
+    pub fn jvm_compile_classpath(
+        &mut self,
+        params: JvmCompileClasspath,
+    ) -> Result<JvmCompileClasspathResult, ClientError> {
+        self.request(|id| Request::BuildTargetJvmCompileClasspath(id, params))
+    }
+
+    /// Send "buildTarget/rustWorkspace"
+    pub fn rust_workspace(
+        &mut self,
+        params: RustWorkspace,
+    ) -> Result<RustWorkspaceResult, ClientError> {
+        self.request(|id| Request::BuildTargetRustWorkspace(id, params))
+    }
+
+    /// Send "workspace/cargoFeaturesState"
+    pub fn cargo_features_state(&mut self) -> Result<CargoFeaturesStateResult, ClientError> {
+        self.request(Request::WorkspaceCargoFeaturesState)
+    }
+
+    /// Send "workspace/setCargoFeatures"
+    pub fn set_cargo_features(
+        &mut self,
+        params: SetCargoFeatures,
+    ) -> Result<SetCargoFeaturesResult, ClientError> {
+        self.request(|id| Request::WorkspaceSetCargoFeatures(id, params))
+    }
+
+    /// Send "debugSession/start"
+    pub fn debug_session_start(
+        &mut self,
+        params: DebugSessionStart,
+    ) -> Result<DebugSessionStartResult, ClientError> {
+        self.request(|id| Request::DebugSessionStart(id, params))
+    }
+
+    /// Send a custom request and deserialize its result.
+    pub fn custom<R: DeserializeOwned>(
+        &mut self,
+        method: impl Into<Cow<'static, str>>,
+        params: Value,
+    ) -> Result<R, ClientError> {
+        self.request(|id| Request::Custom(id, method.into(), params))
+    }
+
+    /// Send a notification to the server.
+    pub fn notify(&self, notification: Notification) -> Result<(), ClientError> {
+        self.conn
+            .send(notification)
+            .map_err(|_| ClientError::Disconnected)
+    }
+
+    /// Send "build/shutdown" followed by "build/exit".
+    pub fn shutdown(mut self) -> Result<(), ClientError> {
+        self.shutdown_server()
+    }
+
+    fn shutdown_server(&mut self) -> Result<(), ClientError> {
+        self.is_shutdown = true;
+        self.request::<Value>(Request::Shutdown)?;
+        self.notify(Notification::Exit)
+    }
+
+    /// Send the request built by `request` and block until the server answers it.
+    fn request<R: DeserializeOwned>(
+        &mut self,
+        request: impl FnOnce(RequestId) -> Request,
+    ) -> Result<R, ClientError> {
+        let req = self.outgoing.register_with(request, "");
+        let (id, method) = (req.id().clone(), req.method().to_string());
+        self.conn.send(req).map_err(|_| ClientError::Disconnected)?;
+
+        loop {
+            let msg = match self.outgoing.next_deadline() {
+                Some(deadline) => self.conn.receiver.recv_deadline(deadline),
+                None => self.conn.receiver.recv().map_err(RecvTimeoutError::from),
+            };
+            match msg {
+                Ok(Message::Response(resp)) if resp.id.as_ref() == Some(&id) => {
+                    self.outgoing.complete(id);
+                    if let Some(err) = resp.error {
+                        return Err(ClientError::Response(err));
+                    }
+                    return Ok(serde_json::from_value(resp.result.unwrap_or_default())?);
+                }
+                Ok(Message::Response(resp)) => {
+                    tracing::warn!("ignoring response to unknown request: {:?}", resp);
+                }
+                Ok(Message::Notification(notification)) => {
+                    let _ = self.notifications.0.send(notification);
+                }
+                Ok(Message::Invalid(invalid)) if invalid.id.as_ref() == Some(&id) => {
+                    self.outgoing.complete(id);
+                    return Err(ClientError::Invalid(invalid));
+                }
+                Ok(Message::Invalid(invalid)) => {
+                    tracing::warn!("skipping {}", invalid);
+                }
+                Ok(Message::Request(req)) => {
+                    let msg = format!("client does not support {}", req.method());
+                    let resp = Response::method_not_found(req.id().clone(), msg);
+                    self.conn
+                        .send(resp)
+                        .map_err(|_| ClientError::Disconnected)?;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    for (id, _) in self.outgoing.expire(std::time::Instant::now()) {
+                        let _ = self.conn.send(Notification::CancelRequest(id));
+                    }
+                    return Err(ClientError::Timeout(method));
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(ClientError::Disconnected),
+            }
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        // A server that failed to initialize does not expect `build/shutdown`.
+        if self.is_shutdown || !self.is_initialized {
+            return;
+        }
+        self.set_timeout(Some(SHUTDOWN_TIMEOUT));
+        if let Err(err) = self.shutdown_server() {
+            tracing::warn!("failed to shut down server: {}", err);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use crate::types::*;
+use crate::{Client, ClientError, Connection, Message, Notification, Request, Response};
+use bsp_server::ErrorCode;
+use std::thread;
+use std::time::Duration;
+
+struct TestServer;
+
+impl BuildServer for TestServer {
+    fn initialize(&self, _params: InitializeBuild) -> anyhow::Result<InitializeBuildResult> {
+        let mut capabilities = ServerCapabilities::default();
+        capabilities.set_compile_provider(Some(CompileProvider::default()));
+        Ok(InitializeBuildResult::new_simple(
+            "TestServer",
+            "0.1",
+            "2.0",
+            capabilities,
+        ))
+    }
+
+    fn workspace_bts(&self) -> anyhow::Result<WorkspaceBuildTargetsResult> {
+        let id = BuildTargetIdentifier::new(Url::parse("file:///tmp/workspace/lib").unwrap());
+        let target = BuildTarget::new_simple(id, vec![], Default::default(), vec![], vec![]);
+        Ok(target.into())
+    }
+
+    fn bt_compile(
+        &self,
+        _params: BuildTargetCompile,
+        _token: &CancellationToken,
+    ) -> anyhow::Result<BuildTargetCompileResult> {
+        anyhow::bail!("compiler not found")
+    }
+}
+
+fn initialize_params() -> InitializeBuild {
+    InitializeBuild::new_simple(
+        "TestClient",
+        "0.1",
+        "2.0",
+        Url::parse("file:///tmp/workspace").unwrap(),
+        Default::default(),
+    )
+}
+
+fn compile_params() -> BuildTargetCompile {
+    let id = BuildTargetIdentifier::new(Url::parse("file:///tmp/workspace/lib").unwrap());
+    BuildTargetCompile::new_simple(vec![id])
+}
+
+/// Answer `build/initialize` and wait for `build/initialized`.
+fn accept(server: &Connection) {
+    let id = match server.receiver.recv().unwrap() {
+        Message::Request(Request::InitializeBuild(id, _)) => id,
+        msg => panic!("expected initialize request, got {:?}", msg),
+    };
+    let result = InitializeBuildResult::new_simple("Raw", "0.1", "2.0", Default::default());
+    server.send(Response::ok(id, result)).unwrap();
+    assert!(matches!(
+        server.receiver.recv().unwrap(),
+        Message::Notification(Notification::Initialized)
+    ));
+}
+
+#[test]
+fn typed_requests() {
+    let (server, conn) = Connection::memory();
+    let handle = thread::spawn(move || server.serve(TestServer));
+
+    let mut client = Client::initialize(conn, initialize_params()).unwrap();
+    assert_eq!(client.server().display_name(), "TestServer");
+
+    let targets = client.workspace_build_targets().unwrap().targets;
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].id.uri(), "file:///tmp/workspace/lib");
+
+    match client.compile(compile_params()) {
+        Err(ClientError::Response(err)) => assert_eq!(err.message, "compiler not found"),
+        res => panic!("expected error response, got {:?}", res),
+    }
+
+    let method = format!("custom/{}", "ping");
+    match client.custom::<serde_json::Value>(method, serde_json::Value::Null) {
+        Err(ClientError::Response(err)) => {
+            assert_eq!(err.code, ErrorCode::MethodNotFound as i32)
+        }
+        res => panic!("expected error response, got {:?}", res),
+    }
+
+    client.shutdown().unwrap();
+    handle.join().unwrap().unwrap();
+}
+
+#[test]
+fn drop_shuts_down_server() {
+    let (server, conn) = Connection::memory();
+    let handle = thread::spawn(move || server.serve(TestServer));
+
+    let client = Client::initialize(conn, initialize_params()).unwrap();
+    drop(client);
+    handle.join().unwrap().unwrap();
+}
+
+#[test]
+fn server_messages_while_waiting() {
+    let (server, conn) = Connection::memory();
+    let handle = thread::spawn(move || {
+        accept(&server);
+        let id = match server.receiver.recv().unwrap() {
+            Message::Request(Request::WorkspaceReload(id)) => id,
+            msg => panic!("expected reload request, got {:?}", msg),
+        };
+        server
+            .send(LogMessage::info("reloading", None, None))
+            .unwrap();
+        let params = serde_json::json!({});
+        server
+            .send(Request::Custom(100.into(), "custom/ask".into(), params))
+            .unwrap();
+        let resp = match server.receiver.recv().unwrap() {
+            Message::Response(resp) => resp,
+            msg => panic!("expected response, got {:?}", msg),
+        };
+        assert_eq!(resp.id, Some(100.into()));
+        assert_eq!(resp.error.unwrap().code, ErrorCode::MethodNotFound as i32);
+        server.send(Response::ok(id, ())).unwrap();
+    });
+
+    let mut client = Client::initialize(conn, initialize_params()).unwrap();
+    client.workspace_reload().unwrap();
+    assert!(matches!(
+        client.notifications().try_recv(),
+        Ok(Notification::LogMessage(msg)) if msg.message == "reloading"
+    ));
+    handle.join().unwrap();
+}
+
+#[test]
+fn request_timeout() {
+    let (server, conn) = Connection::memory();
+    let handle = thread::spawn(move || {
+        accept(&server);
+        // Never answer, keep the connection open until the client is dropped.
+        let (mut requests, mut cancelled) = (vec![], vec![]);
+        while let Ok(msg) = server.receiver.recv() {
+            match msg {
+                Message::Request(req) => requests.push(req.id().clone()),
+                Message::Notification(Notification::CancelRequest(id)) => cancelled.push(id),
+                Message::Notification(Notification::Exit) => break,
+                _ => (),
+            }
+        }
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests, cancelled);
+    });
+
+    let mut client = Client::initialize(conn, initialize_params()).unwrap();
+    client.set_timeout(Some(Duration::from_millis(50)));
+    assert!(matches!(
+        client.workspace_build_targets(),
+        Err(ClientError::Timeout(method)) if method == "workspace/buildTargets"
+    ));
+    assert!(matches!(
+        client.shutdown(),
+        Err(ClientError::Timeout(method)) if method == "build/shutdown"
+    ));
+    handle.join().unwrap();
+}
+
+#[test]
+fn discover_in_precedence_order() {
+    let root = std::env::temp_dir().join(format!("bsp-client-discover-{}", std::process::id()));
+    let (workspace, user) = (root.join("workspace/.bsp"), root.join("user"));
+    std::fs::create_dir_all(&workspace).unwrap();
+    std::fs::create_dir_all(&user).unwrap();
+
+    let details = |name: &str, version: &str| {
+        let argv = vec![format!("{name}-bsp")];
+        BspConnectionDetails::new(name, version, "2.0", vec![], argv)
+    };
+    let write = |dir: &std::path::Path, file: &str, details: &BspConnectionDetails| {
+        std::fs::write(dir.join(file), serde_json::to_vec(details).unwrap()).unwrap()
+    };
+    write(&workspace, "sbt.json", &details("sbt", "1.6"));
+    write(&user, "bloop.json", &details("bloop", "1.5"));
+    write(&user, "sbt.json", &details("sbt", "1.5"));
+    std::fs::write(workspace.join("broken.json"), "{").unwrap();
+    std::fs::write(workspace.join("notes.txt"), "").unwrap();
+
+    let found = crate::discover::discover_in(&[workspace.clone(), user.clone(), root.join("none")]);
+    assert_eq!(
+        found,
+        vec![
+            (workspace.join("sbt.json"), details("sbt", "1.6")),
+            (user.join("bloop.json"), details("bloop", "1.5")),
+        ]
+    );
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn failed_initialize_skips_shutdown() {
+    let (server, conn) = Connection::memory();
+    let handle = thread::spawn(move || {
+        let id = match server.receiver.recv().unwrap() {
+            Message::Request(Request::InitializeBuild(id, _)) => id,
+            msg => panic!("expected initialize request, got {:?}", msg),
+        };
+        server
+            .send(Response::internal_error(id, "no workspace".into()))
+            .unwrap();
+        server.receiver.recv().ok()
+    });
+
+    assert!(matches!(
+        Client::initialize(conn, initialize_params()),
+        Err(ClientError::Response(_))
+    ));
+    // The client went away without sending anything else.
+    assert!(handle.join().unwrap().is_none());
+}
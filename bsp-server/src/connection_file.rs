@@ -0,0 +1,36 @@
+use bsp_types::BspConnectionDetails;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Write `details` to `<workspace_root>/.bsp/<name>.json` so clients can discover and launch
+/// the server, returning the path of the written file.
+///
+/// An existing file with the same name is replaced. Names that are not a plain file name, such
+/// as `..` or names containing path separators, are rejected with an `InvalidInput` error.
+///
+/// # Example
+///
+/// ```no_run
+/// use bsp_server::{install_connection_file, types::BspConnectionDetails};
+///
+/// let exe = std::env::current_exe().unwrap();
+/// let argv = vec![exe.display().to_string(), "--bsp".into()];
+/// let details = BspConnectionDetails::new("MyBuildServer", "0.1", "2.0", vec!["rust".into()], argv);
+/// install_connection_file("/tmp/workspace", &details).unwrap();
+/// ```
+pub fn install_connection_file(
+    workspace_root: impl AsRef<Path>,
+    details: &BspConnectionDetails,
+) -> io::Result<PathBuf> {
+    let name = details.name();
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+        let msg = format!("invalid connection file name {name:?}");
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    }
+    let dir = workspace_root.as_ref().join(".bsp");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(details.file_name());
+    fs::write(&path, serde_json::to_vec_pretty(details)?)?;
+    Ok(path)
+}
@@ -0,0 +1,243 @@
+use crate::languages::Languages;
+use crate::{Incoming, Message, Notification, ProtocolError, Request, RequestId, Response};
+use bsp_types::{BuildServer, CancellationToken, MethodNotFound, TaskFinish, TaskStatus};
+use crossbeam_channel::{bounded, select, unbounded, Receiver, Sender};
+use serde::Serialize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Requests registered by [`watch_cancellation`] that have not been answered yet.
+pub(crate) type Pending = Arc<Mutex<Incoming<CancellationToken>>>;
+
+/// Route a request to the matching [`BuildServer`] method and wrap the outcome in a [`Response`].
+///
+/// `build/initialize` is expected to be handled during the handshake, so receiving it here is
+/// answered with an `InvalidRequest` error. Custom requests are answered with `MethodNotFound`.
+/// Targets of languages the client did not declare are left out of the results.
+pub(crate) fn dispatch<S: BuildServer + ?Sized>(
+    server: &S,
+    req: Request,
+    token: &CancellationToken,
+    languages: &Languages,
+) -> Response {
+    let filter = || languages.lock().unwrap();
+    use Request::*;
+    match req {
+        InitializeBuild(id, _) => {
+            Response::invalid_request(id, "server is already initialized".into())
+        }
+        Shutdown(id) => respond(id, server.build_shutdown()),
+        WorkspaceBuildTargets(id) => respond(
+            id,
+            server
+                .workspace_bts()
+                .map(|result| filter().build_targets(result)),
+        ),
+        WorkspaceReload(id) => respond(id, server.workspace_reload()),
+        BuildTargetDependencyModules(id, params) => {
+            respond(id, server.bt_dependency_modules(params))
+        }
+        DebugSessionStart(id, params) => respond(id, server.debug_session_start(params)),
+        BuildTargetSources(id, params) => respond(
+            id,
+            server.bt_sources(params).map(|result| {
+                refresh_languages(server, languages);
+                filter().sources(result)
+            }),
+        ),
+        TextDocumentInverseSources(id, params) => respond(
+            id,
+            server.bt_inverse_sources(params).map(|result| {
+                refresh_languages(server, languages);
+                filter().inverse_sources(result)
+            }),
+        ),
+        BuildTargetDependencySources(id, params) => {
+            respond(id, server.bt_dependency_sources(params))
+        }
+        BuildTargetResources(id, params) => respond(id, server.bt_resources(params)),
+        BuildTargetRun(id, params) => respond(id, server.bt_run(params, token)),
+        BuildTargetCompile(id, params) => respond(id, server.bt_compile(params, token)),
+        BuildTargetTest(id, params) => respond(id, server.bt_test(params, token)),
+        BuildTargetCleanCache(id, params) => respond(id, server.bt_clean_cache(params)),
+        BuildTargetJvmRunEnvironment(id, params) => {
+            respond(id, server.bt_jvm_run_environment(params))
+        }
+        BuildTargetJvmTestEnvironment(id, params) => {
+            respond(id, server.bt_jvm_test_environment(params))
+        }
+        BuildTargetJvmCompileClasspath(id, params) => {
+            respond(id, server.bt_jvm_compile_classpath(params))
+        }
+        BuildTargetRustWorkspace(id, params) => respond(id, server.bt_rust_workspace(params)),
+        WorkspaceCargoFeaturesState(id) => respond(id, server.workspace_cargo_features_state()),
+        WorkspaceSetCargoFeatures(id, params) => {
+            respond(id, server.workspace_set_cargo_features(params))
+        }
+        Custom(id, method, _) => {
+            Response::method_not_found(id, format!("unsupported method: {method}"))
+        }
+    }
+}
+
+/// Let `languages` know the languages of the targets of `server`, if they were never fetched or
+/// changed since, see [`LanguageFilter::take_stale`](crate::languages::LanguageFilter::take_stale).
+pub(crate) fn refresh_languages<S: BuildServer + ?Sized>(server: &S, languages: &Languages) {
+    if !languages.lock().unwrap().take_stale() {
+        return;
+    }
+    match server.workspace_bts() {
+        Ok(result) => languages.lock().unwrap().learn(&result),
+        Err(err) => tracing::warn!(
+            "failed to get the languages of the build targets: {:#}",
+            err
+        ),
+    }
+}
+
+/// Returns `true` for requests that must not run alongside any other request.
+///
+/// Reloading or cleaning the build state while other requests are in flight would let them
+/// observe a half updated state, and queries sent after a reload expect to see its outcome.
+pub(crate) fn is_exclusive(req: &Request) -> bool {
+    matches!(
+        req,
+        Request::WorkspaceReload(_)
+            | Request::BuildTargetCleanCache(..)
+            | Request::WorkspaceSetCargoFeatures(..)
+    )
+}
+
+/// Execute a pending request and send the resulting messages through `sender`.
+///
+/// A panicking handler is answered with an `InternalError`.
+pub(crate) fn handle<S: BuildServer + ?Sized>(
+    server: &S,
+    req: Request,
+    pending: &Pending,
+    languages: &Languages,
+    sender: &Sender<Message>,
+) -> Result<(), ProtocolError> {
+    let id = req.id().clone();
+    let token = pending.lock().unwrap().get(&id).cloned();
+    let run = || execute(server, req, &token.unwrap_or_default(), languages);
+    let messages = match panic::catch_unwind(AssertUnwindSafe(run)) {
+        Ok(messages) => messages,
+        Err(_) => {
+            tracing::error!("request {} handler panicked", id);
+            let msg = "request handler panicked".to_string();
+            vec![Response::internal_error(id.clone(), msg).into()]
+        }
+    };
+    pending.lock().unwrap().complete(id);
+    for msg in messages {
+        sender.send(msg).map_err(|e| ProtocolError(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Run a request unless it was cancelled, returning the messages to send back to the client.
+///
+/// If the request is cancelled before or while its handler runs, the handler's result is
+/// replaced with a `RequestCanceled` error, preceded by a cancelled [`TaskFinish`] for every task
+/// the handler left open.
+pub(crate) fn execute<S: BuildServer + ?Sized>(
+    server: &S,
+    req: Request,
+    token: &CancellationToken,
+    languages: &Languages,
+) -> Vec<Message> {
+    let id = req.id().clone();
+    if !token.is_cancelled() {
+        let resp = dispatch(server, req, token, languages);
+        if !token.is_cancelled() {
+            return vec![resp.into()];
+        }
+    }
+
+    tracing::info!("request {} was cancelled", id);
+    let mut messages: Vec<Message> = token
+        .take_tasks()
+        .into_iter()
+        .map(|task_id| {
+            let mut finish = TaskFinish::new(task_id);
+            finish.status = TaskStatus::Cancelled;
+            finish.into()
+        })
+        .collect();
+    messages.push(Response::request_canceled(id, "canceled by client".into()).into());
+    messages
+}
+
+/// Forward messages from `receiver`, handling `$/cancelRequest` notifications on the way.
+///
+/// Every forwarded request is registered in `incoming` with a fresh cancellation token before it
+/// is forwarded, and the token is cancelled as soon as a matching `$/cancelRequest` arrives, even
+/// while a handler is still busy with an earlier request. Forwarding goes on until `build/exit`
+/// or until the returned [`Watcher`] is stopped.
+pub(crate) fn watch_cancellation(
+    receiver: Receiver<Message>,
+    incoming: Pending,
+) -> (Receiver<Message>, Watcher) {
+    let (sender, forwarded) = unbounded();
+    let (stop, stopped) = bounded::<()>(0);
+    let thread = thread::spawn(move || loop {
+        let msg = select! {
+            recv(receiver) -> msg => match msg {
+                Ok(msg) => msg,
+                Err(_) => break,
+            },
+            recv(stopped) -> _ => break,
+        };
+        match &msg {
+            Message::Notification(Notification::CancelRequest(id)) => {
+                if !incoming.lock().unwrap().cancel_token(id) {
+                    tracing::debug!("ignoring cancellation of unknown request {}", id);
+                }
+                continue;
+            }
+            Message::Request(req) => {
+                let token = CancellationToken::default();
+                incoming.lock().unwrap().register(req.id().clone(), token);
+            }
+            _ => (),
+        };
+        let is_exit = matches!(&msg, Message::Notification(Notification::Exit));
+        if sender.send(msg).is_err() || is_exit {
+            break;
+        }
+    });
+    (forwarded, Watcher { stop, thread })
+}
+
+/// The thread forwarding messages for [`watch_cancellation`].
+pub(crate) struct Watcher {
+    stop: Sender<()>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl Watcher {
+    /// Stop forwarding messages and wait for the thread to finish.
+    pub(crate) fn stop(self) {
+        drop(self.stop);
+        if self.thread.join().is_err() {
+            tracing::error!("cancellation watcher panicked");
+        }
+    }
+}
+
+/// Convert a handler result into a response, reporting failures as internal errors.
+///
+/// Methods left unimplemented by the server fail with [`MethodNotFound`], which is reported as
+/// such to the client.
+pub(crate) fn respond<R: Serialize>(id: RequestId, result: anyhow::Result<R>) -> Response {
+    match result {
+        Ok(value) => Response::ok(id, value),
+        Err(err) if err.is::<MethodNotFound>() => Response::method_not_found(id, err.to_string()),
+        Err(err) => {
+            tracing::error!("request {} failed: {:#}", id, err);
+            Response::internal_error(id, err.to_string())
+        }
+    }
+}
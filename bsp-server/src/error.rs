@@ -1,5 +1,6 @@
 use crate::Notification;
 use crate::Request;
+use crate::{RequestId, Response, ResponseError};
 use std::fmt;
 
 #[derive(Debug, Clone)]
@@ -19,6 +20,51 @@ impl From<anyhow::Error> for ProtocolError {
     }
 }
 
+/// A message received from the other side that could not be decoded.
+///
+/// The reader skips such messages and forwards them as [`crate::Message::Invalid`]. Requests,
+/// and messages that could not be identified at all, are answered with the error
+/// [`InvalidMessage::response`] returns.
+#[derive(Debug, Clone)]
+pub struct InvalidMessage {
+    /// The error the message is rejected with.
+    pub code: ErrorCode,
+    /// The id of the message, if it could be decoded.
+    pub id: Option<RequestId>,
+    /// Why the message is invalid.
+    pub error: String,
+    /// Whether the other side expects an answer.
+    expects_response: bool,
+}
+
+impl InvalidMessage {
+    pub(crate) fn new(
+        code: ErrorCode,
+        id: Option<RequestId>,
+        error: impl fmt::Display,
+        expects_response: bool,
+    ) -> Self {
+        Self {
+            code,
+            id,
+            error: error.to_string(),
+            expects_response,
+        }
+    }
+
+    /// The error response to send back, `None` for invalid notifications and responses.
+    pub fn response(&self) -> Option<Response> {
+        self.expects_response
+            .then(|| Response::err(self.id.clone(), self.code as i32, self.error.clone()))
+    }
+}
+
+impl fmt::Display for InvalidMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid message ({:?}): {}", self.code, self.error)
+    }
+}
+
 #[derive(Debug)]
 pub enum ExtractError<T> {
     /// The extracted message was of a different method than expected.
@@ -28,6 +74,8 @@ pub enum ExtractError<T> {
         method: String,
         error: serde_json::Error,
     },
+    /// The extracted response carries an error instead of a result.
+    ResponseError(ResponseError),
 }
 
 impl std::error::Error for ExtractError<Request> {}
@@ -40,6 +88,9 @@ impl fmt::Display for ExtractError<Request> {
             ExtractError::JsonError { method, error } => {
                 write!(f, "Invalid request\nMethod: {method}\n error: {error}",)
             }
+            ExtractError::ResponseError(err) => {
+                write!(f, "Request failed with error {}: {}", err.code, err.message)
+            }
         }
     }
 }
@@ -54,11 +105,31 @@ impl fmt::Display for ExtractError<Notification> {
             ExtractError::JsonError { method, error } => {
                 write!(f, "Invalid notification\nMethod: {method}\n error: {error}")
             }
+            ExtractError::ResponseError(err) => {
+                write!(f, "Request failed with error {}: {}", err.code, err.message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExtractError<Response> {}
+impl fmt::Display for ExtractError<Response> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::MethodMismatch(resp) => {
+                write!(f, "Unexpected response to request {:?}", resp.id)
+            }
+            ExtractError::JsonError { method, error } => {
+                write!(f, "Invalid response\nMethod: {method}\n error: {error}")
+            }
+            ExtractError::ResponseError(err) => {
+                write!(f, "Request failed with error {}: {}", err.code, err.message)
+            }
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[allow(unused)]
 pub enum ErrorCode {
     ParseError = -32700,
@@ -0,0 +1,368 @@
+use std::io::{self, BufRead, BufWriter, Read, Write};
+
+/// Limits applied to incoming frames.
+///
+/// The predefined transports use [`Framing::default`]. For other limits, build the stream
+/// yourself and pass it to [`crate::Connection::from_io_with_framing`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Framing {
+    max_message_size: usize,
+    max_header_size: usize,
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Self {
+            max_message_size: 64 * 1024 * 1024,
+            max_header_size: 8 * 1024,
+        }
+    }
+}
+
+impl Framing {
+    pub fn new(max_message_size: usize, max_header_size: usize) -> Self {
+        Self {
+            max_message_size,
+            max_header_size,
+        }
+    }
+
+    /// Get the largest accepted `Content-Length`. Larger frames are skipped without being
+    /// buffered.
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    /// Set the largest accepted `Content-Length`.
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.max_message_size = max_message_size;
+    }
+
+    /// Get the largest accepted header line. Longer lines are skipped without being buffered.
+    pub fn max_header_size(&self) -> usize {
+        self.max_header_size
+    }
+
+    /// Set the largest accepted header line.
+    pub fn set_max_header_size(&mut self, max_header_size: usize) {
+        self.max_header_size = max_header_size;
+    }
+}
+
+/// Reads `Content-Length` delimited frames, keeping track of the stream offset for errors.
+pub(crate) struct FrameReader<R> {
+    inner: R,
+    framing: Framing,
+    offset: u64,
+}
+
+impl<R: BufRead> FrameReader<R> {
+    pub(crate) fn new(inner: R, framing: Framing) -> Self {
+        Self {
+            inner,
+            framing,
+            offset: 0,
+        }
+    }
+
+    /// Read the body of the next frame, returning `None` at end of input.
+    ///
+    /// A frame with malformed headers is consumed up to the end of its headers, and its body is
+    /// skipped if its length is known, before failing with `InvalidData` so that reading can
+    /// resume at the next frame. Lines before the headers that are not headers, such as the tail
+    /// of a body longer than announced, and header lines longer than the limit are skipped.
+    pub(crate) fn read_frame(&mut self) -> io::Result<Option<String>> {
+        let mut size = None;
+        let mut error = None;
+        let mut in_headers = false;
+        let mut buf = String::new();
+
+        loop {
+            let start = self.offset;
+            buf.clear();
+            let read = self.read_line(&mut buf)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            if read == self.framing.max_header_size && !buf.ends_with('\n') {
+                self.skip_line()?;
+                tracing::warn!(
+                    "skipping header of more than {} bytes at byte {}",
+                    self.framing.max_header_size,
+                    start
+                );
+                continue;
+            }
+            if !in_headers {
+                let line = buf.trim_end_matches(['\r', '\n']);
+                if !is_header(line) {
+                    tracing::warn!("skipping garbage at byte {}: {:?}", start, line);
+                    continue;
+                }
+                in_headers = true;
+            }
+            let line = match buf.strip_suffix("\r\n") {
+                Some(line) => line,
+                None => {
+                    let msg = format!("malformed header {:?}", buf);
+                    error.get_or_insert(header_error(start, msg));
+                    continue;
+                }
+            };
+            if line.is_empty() {
+                break;
+            }
+            if let Err(msg) = self.parse_header(line, &mut size) {
+                error.get_or_insert(header_error(start, msg));
+            }
+        }
+
+        let size = match (size, error) {
+            (Some(size), None) if size <= self.framing.max_message_size => size,
+            (Some(size), error) => {
+                let error = error.unwrap_or_else(|| {
+                    let max = self.framing.max_message_size;
+                    let msg = format!("message of {size} bytes exceeds the limit of {max} bytes");
+                    header_error(self.offset, msg)
+                });
+                self.skip(size as u64)?;
+                return Err(error);
+            }
+            (None, error) => {
+                let msg = "missing Content-Length header".to_string();
+                return Err(error.unwrap_or_else(|| header_error(self.offset, msg)));
+            }
+        };
+
+        let mut body = vec![0; size];
+        self.inner.read_exact(&mut body)?;
+        self.offset += size as u64;
+        let body = String::from_utf8(body).map_err(|err| {
+            let msg = format!("invalid utf-8 in body at byte {}: {}", self.offset, err);
+            io::Error::new(io::ErrorKind::InvalidData, msg)
+        })?;
+        Ok(Some(body))
+    }
+
+    /// Read a header line of at most `max_header_size` bytes.
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        let limit = self.framing.max_header_size as u64;
+        let read = (&mut self.inner).take(limit).read_line(buf)?;
+        self.offset += read as u64;
+        Ok(read)
+    }
+
+    /// Skip the rest of the current line.
+    fn skip_line(&mut self) -> io::Result<()> {
+        loop {
+            let available = self.inner.fill_buf()?;
+            let (len, done) = match available.iter().position(|b| *b == b'\n') {
+                Some(pos) => (pos + 1, true),
+                None => (available.len(), available.is_empty()),
+            };
+            self.inner.consume(len);
+            self.offset += len as u64;
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip(&mut self, size: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.inner).take(size), &mut io::sink())?;
+        self.offset += skipped;
+        if skipped < size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+
+    fn parse_header(&self, line: &str, size: &mut Option<usize>) -> Result<(), String> {
+        let (name, value) = line
+            .split_once(':')
+            .filter(|_| is_header(line))
+            .ok_or_else(|| format!("malformed header {:?}", line))?;
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("Content-Length") {
+            let value = value
+                .parse()
+                .map_err(|err| format!("invalid Content-Length {:?}: {}", value, err))?;
+            *size = Some(value);
+        } else if name.eq_ignore_ascii_case("Content-Type") {
+            check_content_type(value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialization buffers larger than this are released after use instead of being kept around
+/// for the next message.
+const MAX_RETAINED_BUFFER: usize = 1024 * 1024;
+
+/// Writes `Content-Length` delimited frames, reusing one serialization buffer across messages.
+pub(crate) struct FrameWriter<W: Write> {
+    inner: BufWriter<W>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> FrameWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner: BufWriter::new(inner),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Write the body produced by `serialize` as one frame, flushing once it is written.
+    pub(crate) fn write_frame(
+        &mut self,
+        serialize: impl FnOnce(&mut Vec<u8>) -> serde_json::Result<()>,
+    ) -> io::Result<()> {
+        self.buf.clear();
+        serialize(&mut self.buf)?;
+        write!(self.inner, "Content-Length: {}\r\n\r\n", self.buf.len())?;
+        self.inner.write_all(&self.buf)?;
+        self.inner.flush()?;
+        if self.buf.capacity() > MAX_RETAINED_BUFFER {
+            self.buf = Vec::new();
+        }
+        Ok(())
+    }
+}
+
+/// Accept JSON-RPC content types encoded as utf-8, the only encoding the protocol supports.
+fn check_content_type(value: &str) -> Result<(), String> {
+    let mut parts = value.split(';').map(str::trim);
+    let mime = parts.next().unwrap_or_default();
+    if !["application/vscode-jsonrpc", "application/json"]
+        .iter()
+        .any(|expected| mime.eq_ignore_ascii_case(expected))
+    {
+        return Err(format!("unsupported Content-Type {:?}", mime));
+    }
+    for param in parts {
+        let Some((name, charset)) = param.split_once('=') else {
+            continue;
+        };
+        let charset = charset.trim().trim_matches('"');
+        if name.trim().eq_ignore_ascii_case("charset")
+            && !charset.eq_ignore_ascii_case("utf-8")
+            && !charset.eq_ignore_ascii_case("utf8")
+        {
+            return Err(format!("unsupported charset {:?}", charset));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `line` starts with a header name followed by a colon.
+fn is_header(line: &str) -> bool {
+    let Some((name, _)) = line.split_once(':') else {
+        return false;
+    };
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+fn header_error(offset: u64, msg: String) -> io::Error {
+    let msg = format!("{} at byte {}", msg, offset);
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader(input: &str, framing: Framing) -> FrameReader<io::Cursor<Vec<u8>>> {
+        FrameReader::new(io::Cursor::new(input.as_bytes().to_vec()), framing)
+    }
+
+    fn error(result: io::Result<Option<String>>) -> String {
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        err.to_string()
+    }
+
+    #[test]
+    fn headers() {
+        let input =
+            "content-length:2\r\nCONTENT-TYPE: application/vscode-jsonrpc; charset=utf8\r\n\r\n{}"
+                .to_string()
+                + "Content-Length: 2\r\nContent-Type: application/json; charset=latin1\r\n\r\n{}"
+                + "Content-Type: text/plain\r\nContent-Length: 2\r\n\r\n{}"
+                + "Content-Length: 2\r\n\r\n[]";
+        let mut reader = reader(&input, Framing::default());
+
+        assert_eq!(reader.read_frame().unwrap().unwrap(), "{}");
+        assert_eq!(
+            error(reader.read_frame()),
+            "unsupported charset \"latin1\" at byte 97"
+        );
+        assert_eq!(
+            error(reader.read_frame()),
+            "unsupported Content-Type \"text/plain\" at byte 149"
+        );
+        assert_eq!(reader.read_frame().unwrap().unwrap(), "[]");
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn write_frames() {
+        let mut output = vec![];
+        let mut writer = FrameWriter::new(&mut output);
+        writer
+            .write_frame(|buf| serde_json::to_writer(buf, &[1, 2]))
+            .unwrap();
+        writer
+            .write_frame(|buf| {
+                serde_json::to_writer(buf, "x".repeat(2 * MAX_RETAINED_BUFFER).as_str())
+            })
+            .unwrap();
+        assert_eq!(writer.buf.capacity(), 0);
+        writer
+            .write_frame(|buf| serde_json::to_writer(buf, &()))
+            .unwrap();
+        drop(writer);
+
+        let mut reader = reader(std::str::from_utf8(&output).unwrap(), Framing::default());
+        assert_eq!(reader.read_frame().unwrap().unwrap(), "[1,2]");
+        assert_eq!(
+            reader.read_frame().unwrap().unwrap().len(),
+            2 * MAX_RETAINED_BUFFER + 2
+        );
+        assert_eq!(reader.read_frame().unwrap().unwrap(), "null");
+    }
+
+    #[test]
+    fn size_limits() {
+        let framing = Framing::new(4, 32);
+        let body = "x".repeat(10);
+        let input = format!("Content-Length: 10\r\n\r\n{body}")
+            + &format!("X-Long: {body}{body}{body}\r\nContent-Length: 2\r\n\r\n{{}}")
+            + &format!("Content-Length: 2\r\nX-Long: {body}{body}{body}{body}\r\n\r\n[]");
+        let mut reader = reader(&input, framing);
+
+        assert_eq!(
+            error(reader.read_frame()),
+            "message of 10 bytes exceeds the limit of 4 bytes at byte 22"
+        );
+        assert_eq!(reader.read_frame().unwrap().unwrap(), "{}");
+        assert_eq!(reader.read_frame().unwrap().unwrap(), "[]");
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn resync_at_line_start() {
+        let input = "}\r\n\r\nContent-Length: 2\r\n\r\n{}".to_string()
+            + "X-Note: Content-Length: 9\r\nContent-Length: 2\r\n\r\n[]"
+            + "garbage Content-Length: 2\r\n\r\n{}";
+        let mut reader = reader(&input, Framing::default());
+
+        assert_eq!(reader.read_frame().unwrap().unwrap(), "{}");
+        assert_eq!(reader.read_frame().unwrap().unwrap(), "[]");
+        // Not a header line, so neither is the frame it would announce.
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+}
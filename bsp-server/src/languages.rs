@@ -0,0 +1,254 @@
+use crate::{Message, Notification};
+use bsp_types::{
+    BuildTargetDidChange, BuildTargetInverseSourcesResult, BuildTargetSourcesResult,
+    WorkspaceBuildTargetsResult,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Shared between the request handlers of a connection, see [`LanguageFilter`].
+pub(crate) type Languages = Arc<Mutex<LanguageFilter>>;
+
+/// Keeps build targets of languages the client did not declare in `build/initialize` out of
+/// what the server sends.
+///
+/// Targets are matched by the language ids the server reports for them in its
+/// `workspace/buildTargets` results, so that they are also left out of the results and
+/// notifications that only refer to targets by id. The targets are fetched again at most once
+/// after each `buildTarget/didChange`, see [`LanguageFilter::take_stale`]. Targets without
+/// languages and unknown targets are kept, and nothing is filtered if the client declared no
+/// language.
+#[derive(Debug, Default)]
+pub(crate) struct LanguageFilter {
+    language_ids: Vec<String>,
+    /// Language ids of the targets of the last `workspace/buildTargets` result, by uri.
+    targets: HashMap<String, Vec<String>>,
+    /// Whether `targets` may be outdated, since they were never learned or changed since.
+    stale: bool,
+}
+
+impl LanguageFilter {
+    pub(crate) fn set_language_ids(&mut self, language_ids: Vec<String>) {
+        self.language_ids = language_ids;
+        self.stale = true;
+    }
+
+    /// Returns `true` if the client declared languages, so targets have to be filtered.
+    pub(crate) fn is_active(&self) -> bool {
+        !self.language_ids.is_empty()
+    }
+
+    /// Returns `true` if targets are filtered and have to be fetched again, expecting the caller
+    /// to do so: later calls return `false` until the targets change.
+    pub(crate) fn take_stale(&mut self) -> bool {
+        self.is_active() && std::mem::take(&mut self.stale)
+    }
+
+    /// Remember the languages of the targets of `result`.
+    pub(crate) fn learn(&mut self, result: &WorkspaceBuildTargetsResult) {
+        self.stale = false;
+        self.targets = result
+            .targets
+            .iter()
+            .map(|target| (target.id.uri().to_string(), target.language_ids.clone()))
+            .collect();
+    }
+
+    fn allows(&self, language_ids: &[String]) -> bool {
+        !self.is_active()
+            || language_ids.is_empty()
+            || language_ids.iter().any(|id| self.language_ids.contains(id))
+    }
+
+    fn allows_target(&self, uri: &str) -> bool {
+        match self.targets.get(uri) {
+            Some(language_ids) => self.allows(language_ids),
+            None => true,
+        }
+    }
+
+    fn warn_dropped(&self, method: &str, dropped: &[&str]) {
+        if !dropped.is_empty() {
+            tracing::warn!(
+                "{} returned targets for languages the client did not declare ({:?}), \
+                 dropping {:?}",
+                method,
+                self.language_ids,
+                dropped
+            );
+        }
+    }
+
+    pub(crate) fn build_targets(
+        &mut self,
+        mut result: WorkspaceBuildTargetsResult,
+    ) -> WorkspaceBuildTargetsResult {
+        self.learn(&result);
+        let (kept, dropped): (Vec<_>, Vec<_>) = std::mem::take(&mut result.targets)
+            .into_iter()
+            .partition(|target| self.allows(&target.language_ids));
+        let uris: Vec<_> = dropped.iter().map(|target| target.id.uri()).collect();
+        self.warn_dropped("workspace/buildTargets", &uris);
+        result.targets = kept;
+        result
+    }
+
+    pub(crate) fn sources(&self, mut result: BuildTargetSourcesResult) -> BuildTargetSourcesResult {
+        let (kept, dropped): (Vec<_>, Vec<_>) = std::mem::take(result.items_mut())
+            .into_iter()
+            .partition(|item| self.allows_target(item.target().uri()));
+        let uris: Vec<_> = dropped.iter().map(|item| item.target().uri()).collect();
+        self.warn_dropped("buildTarget/sources", &uris);
+        result.set_items(kept);
+        result
+    }
+
+    pub(crate) fn inverse_sources(
+        &self,
+        mut result: BuildTargetInverseSourcesResult,
+    ) -> BuildTargetInverseSourcesResult {
+        let (kept, dropped): (Vec<_>, Vec<_>) = result
+            .targets()
+            .iter()
+            .cloned()
+            .partition(|target| self.allows_target(target.uri()));
+        let uris: Vec<_> = dropped.iter().map(|target| target.uri()).collect();
+        self.warn_dropped("textDocument/inverseSources", &uris);
+        result.set_targets(kept);
+        result
+    }
+
+    /// Drop the events about targets of other languages, and the whole notification if none is left.
+    ///
+    /// The targets are marked stale, to be fetched again before filtering the next result.
+    pub(crate) fn did_change(
+        &mut self,
+        mut params: BuildTargetDidChange,
+    ) -> Option<BuildTargetDidChange> {
+        let (kept, dropped): (Vec<_>, Vec<_>) = std::mem::take(&mut params.changes)
+            .into_iter()
+            .partition(|event| self.allows_target(event.target.uri()));
+        let uris: Vec<_> = dropped.iter().map(|event| event.target.uri()).collect();
+        self.warn_dropped("buildTarget/didChange", &uris);
+        params.changes = kept;
+        self.stale = true;
+        (!params.changes.is_empty()).then_some(params)
+    }
+
+    /// Filter a message sent by the server, returning `None` if nothing is left to send.
+    pub(crate) fn message(&mut self, msg: Message) -> Option<Message> {
+        match msg {
+            Message::Notification(Notification::BuildTargetDidChange(params)) => {
+                self.did_change(params).map(Into::into)
+            }
+            msg => Some(msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bsp_types::{BuildTarget, BuildTargetEvent, BuildTargetIdentifier, SourcesCollection, Url};
+
+    fn id(name: &str) -> BuildTargetIdentifier {
+        BuildTargetIdentifier::new(Url::parse(&format!("file:///tmp/workspace/{name}")).unwrap())
+    }
+
+    fn target(name: &str, language_ids: &[&str]) -> BuildTarget {
+        let mut target =
+            BuildTarget::new_simple(id(name), vec![], Default::default(), vec![], vec![]);
+        target.language_ids = language_ids.iter().map(|id| id.to_string()).collect();
+        target
+    }
+
+    fn names(ids: impl Iterator<Item = String>) -> Vec<String> {
+        ids.map(|uri| uri.rsplit('/').next().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn drops_undeclared_languages() {
+        let mut filter = LanguageFilter::default();
+        filter.set_language_ids(vec!["rust".into()]);
+
+        let result = WorkspaceBuildTargetsResult {
+            targets: vec![
+                target("lib", &["rust"]),
+                target("app", &["scala", "java"]),
+                target("docs", &[]),
+            ],
+        };
+        let result = filter.build_targets(result);
+        let kept = result.targets.iter().map(|t| t.id.uri().to_string());
+        assert_eq!(names(kept), ["lib", "docs"]);
+
+        let sources = BuildTargetSourcesResult::new(vec![
+            SourcesCollection::new(id("lib"), vec![]),
+            SourcesCollection::new(id("app"), vec![]),
+        ]);
+        let kept = filter.sources(sources);
+        let kept = kept.items().iter().map(|i| i.target().uri().to_string());
+        assert_eq!(names(kept), ["lib"]);
+
+        let inverse = BuildTargetInverseSourcesResult::new(vec![id("app"), id("docs")]);
+        let kept = filter.inverse_sources(inverse);
+        let kept = kept.targets().iter().map(|t| t.uri().to_string());
+        assert_eq!(names(kept), ["docs"]);
+
+        let change = |name| BuildTargetDidChange {
+            changes: vec![BuildTargetEvent::new_simple(id(name))],
+        };
+        assert!(filter.did_change(change("app")).is_none());
+        assert!(filter.did_change(change("lib")).is_some());
+    }
+
+    #[test]
+    fn unknown_targets_are_kept() {
+        let mut filter = LanguageFilter::default();
+        filter.set_language_ids(vec!["rust".into()]);
+        let sources = || {
+            BuildTargetSourcesResult::new(vec![
+                SourcesCollection::new(id("lib"), vec![]),
+                SourcesCollection::new(id("app"), vec![]),
+            ])
+        };
+        assert_eq!(filter.sources(sources()).items().len(), 2);
+
+        filter.learn(&WorkspaceBuildTargetsResult {
+            targets: vec![target("lib", &["rust"]), target("app", &["scala"])],
+        });
+        let kept = filter.sources(sources());
+        let kept = kept.items().iter().map(|i| i.target().uri().to_string());
+        assert_eq!(names(kept), ["lib"]);
+    }
+
+    #[test]
+    fn stale_after_did_change() {
+        let mut filter = LanguageFilter::default();
+        assert!(!filter.take_stale());
+        filter.set_language_ids(vec!["rust".into()]);
+        assert!(filter.take_stale());
+        assert!(!filter.take_stale());
+
+        let change = BuildTargetDidChange {
+            changes: vec![BuildTargetEvent::new_simple(id("new"))],
+        };
+        assert!(filter.did_change(change).is_some());
+        assert!(filter.take_stale());
+        assert!(!filter.take_stale());
+
+        filter.set_language_ids(vec!["rust".into()]);
+        filter.learn(&WorkspaceBuildTargetsResult { targets: vec![] });
+        assert!(!filter.take_stale());
+    }
+
+    #[test]
+    fn no_declared_languages() {
+        let mut filter = LanguageFilter::default();
+        let result = WorkspaceBuildTargetsResult {
+            targets: vec![target("app", &["scala"])],
+        };
+        assert_eq!(filter.build_targets(result).targets.len(), 1);
+    }
+}
@@ -5,55 +5,172 @@
 //! Run with `RUST_LOG=bsp_server=trace` to see all the messages.
 //!
 //! inspired by lsp-server
+mod connection_file;
+mod dispatch;
 mod error;
+mod framing;
 mod io_thread;
+mod languages;
+mod lifecycle;
 mod message;
-// mod req_queue;
+pub mod methods;
+mod pool;
+mod process;
+mod req_queue;
 mod request;
 mod response;
 mod transporter;
+mod version;
 
 mod notification;
 #[cfg(test)]
 mod tests;
 pub use bsp_types as types;
-pub use error::{ErrorCode, ExtractError, ProtocolError};
+pub use connection_file::install_connection_file;
+pub use error::{ErrorCode, ExtractError, InvalidMessage, ProtocolError};
+pub use framing::Framing;
 pub use io_thread::IoThreads;
+pub use lifecycle::LifecycleState;
 pub use message::Message;
+pub use methods::{BspNotification, BspRequest};
 pub use notification::Notification;
-// pub use req_queue::{Incoming, Outgoing, ReqQueue};
+pub use process::ServerProcess;
+pub use req_queue::{Incoming, Outgoing, PendingResponse, ReqQueue};
 pub use request::{Request, RequestId};
 pub use response::{Response, ResponseError};
 pub(crate) use transporter::Transporter;
+pub use version::BspVersion;
 
-use bsp_types::InitializeBuild;
-use crossbeam_channel::{unbounded, Receiver, SendError, SendTimeoutError, Sender, TrySendError};
-use serde::Serialize;
+use bsp_types::{BuildServer, InitializeBuild, ServerCapabilities};
+use crossbeam_channel::{
+    bounded, unbounded, Receiver, Select, SelectTimeoutError, SendError, SendTimeoutError, Sender,
+    TrySendError,
+};
+use serde::{Deserialize, Serialize};
 use std::io;
 use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::{
+    fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+    net::{UnixListener, UnixStream},
+};
+#[cfg(unix)]
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 /// Connection is just a pair of channels of LSP messages.
+///
+/// On connections accepting a client ([`Connection::stdio`], [`Connection::listen`], ...), the
+/// methods driving the server ([`Connection::initialize`], [`Connection::recv`],
+/// [`Connection::handle_shutdown`], [`Connection::serve`], ...) keep track of the
+/// [`LifecycleState`], answering requests that are not allowed in the current state with the
+/// error the spec mandates. Requests for features the server did not advertise during
+/// initialization are answered with `MethodNotFound`, see [`Connection::set_check_capabilities`].
+/// Messages read from `receiver` directly bypass these checks, and connections to a server
+/// ([`Connection::connect`], [`Connection::spawn`], ...) pass every message through.
 pub struct Connection {
     pub sender: Sender<Message>,
     pub receiver: Receiver<Message>,
+    /// `None` until the connection is known to accept a client.
+    lifecycle: Mutex<Option<lifecycle::Lifecycle>>,
+    check_capabilities: bool,
+    languages: languages::Languages,
+    /// The BSP version negotiated during initialization.
+    bsp_version: Mutex<Option<BspVersion>>,
+    /// Requests received while serving, with the tokens cancelling them.
+    pending: dispatch::Pending,
+    /// Requests sent to the client, see [`Connection::send_request`].
+    outgoing: Mutex<Outgoing<Sender<Response>>>,
+    /// Wakes up [`Connection::recv_until`] when a request is sent.
+    wake: (Sender<()>, Receiver<()>),
 }
 
 impl Connection {
+    pub(crate) fn new(sender: Sender<Message>, receiver: Receiver<Message>) -> Self {
+        Self {
+            sender,
+            receiver,
+            lifecycle: Default::default(),
+            check_capabilities: true,
+            languages: Default::default(),
+            bsp_version: Default::default(),
+            pending: Default::default(),
+            outgoing: Default::default(),
+            wake: bounded(1),
+        }
+    }
+
+    fn from_transporter(transporter: Transporter) -> (Connection, IoThreads) {
+        let Transporter(sender, receiver, io_threads) = transporter;
+        (Connection::new(sender, receiver), io_threads)
+    }
+
+    /// Like [`Connection::from_transporter`], for a connection accepting a client.
+    fn accepting(transporter: Transporter) -> (Connection, IoThreads) {
+        let (conn, io_threads) = Self::from_transporter(transporter);
+        conn.track_lifecycle();
+        (conn, io_threads)
+    }
+
+    /// Apply the lifecycle checks from now on, if they are not applied already.
+    fn track_lifecycle(&self) {
+        self.lifecycle
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Default::default);
+    }
+
     /// Create connection over standard in/standard out.
     ///
     /// Use this to create a real language server.
     pub fn stdio() -> (Connection, IoThreads) {
-        let Transporter(sender, receiver, io_threads) = Transporter::stdio();
-        (Connection { sender, receiver }, io_threads)
+        Self::accepting(Transporter::stdio())
+    }
+
+    /// Create connection over any reader and writer pair, such as pipes, pty pairs or in-memory
+    /// buffers, using the same framing as the other transports.
+    ///
+    /// The lifecycle checks apply once the connection is used to initialize a server.
+    pub fn from_io<R, W>(reader: R, writer: W) -> (Connection, IoThreads)
+    where
+        R: io::BufRead + Send + 'static,
+        W: io::Write + Send + 'static,
+    {
+        Self::from_io_with_framing(reader, writer, Framing::default())
+    }
+
+    /// Like [`Connection::from_io`], with custom limits for incoming frames.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use bsp_server::{Connection, Framing};
+    /// use std::io;
+    ///
+    /// let mut framing = Framing::default();
+    /// framing.set_max_message_size(16 * 1024 * 1024);
+    /// let stdin = io::BufReader::new(io::stdin());
+    /// let (conn, io_threads) = Connection::from_io_with_framing(stdin, io::stdout(), framing);
+    /// ```
+    pub fn from_io_with_framing<R, W>(
+        reader: R,
+        writer: W,
+        framing: Framing,
+    ) -> (Connection, IoThreads)
+    where
+        R: io::BufRead + Send + 'static,
+        W: io::Write + Send + 'static,
+    {
+        Self::from_transporter(Transporter::new(reader, writer, framing))
     }
 
     /// Open a connection over tcp.
     /// This call blocks until a connection is established.
     pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<(Connection, IoThreads)> {
         let stream = TcpStream::connect(addr)?;
-        let Transporter(sender, receiver, io_threads) = Transporter::socket(stream);
-        Ok((Connection { sender, receiver }, io_threads))
+        Ok(Self::from_transporter(Transporter::socket(stream)))
     }
 
     /// Listen for a connection over tcp.
@@ -61,23 +178,94 @@ impl Connection {
     pub fn listen<A: ToSocketAddrs>(addr: A) -> io::Result<(Connection, IoThreads)> {
         let listener = TcpListener::bind(addr)?;
         let (stream, _) = listener.accept()?;
-        let Transporter(sender, receiver, io_threads) = Transporter::socket(stream);
-        Ok((Connection { sender, receiver }, io_threads))
+        Ok(Self::accepting(Transporter::socket(stream)))
+    }
+
+    /// Open a connection over a unix domain socket.
+    #[cfg(unix)]
+    pub fn connect_unix<P: AsRef<Path>>(path: P) -> io::Result<(Connection, IoThreads)> {
+        let stream = UnixStream::connect(path)?;
+        Ok(Self::from_transporter(Transporter::unix(stream)))
     }
 
-    /// Creates a pair of connected connections in memory for testing.
+    /// Listen for a connection over a unix domain socket at `path`.
+    /// This call blocks until a connection is established.
+    ///
+    /// A stale socket file left by a previous server is removed, while a socket some other server
+    /// is still listening on results in an `AddrInUse` error, and any other file at `path` in an
+    /// `AlreadyExists` error. The socket is only accessible by the owning user.
+    #[cfg(unix)]
+    pub fn listen_unix<P: AsRef<Path>>(path: P) -> io::Result<(Connection, IoThreads)> {
+        let path = path.as_ref();
+        if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            if UnixStream::connect(path).is_ok() {
+                let msg = format!("{} is used by another server", path.display());
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, msg));
+            }
+            std::fs::remove_file(path)?;
+        }
+        let listener = Self::bind_unix(path)?;
+        let (stream, _) = listener.accept()?;
+        Ok(Self::accepting(Transporter::unix(stream)))
+    }
+
+    /// Bind a socket at `path` that only the owning user can connect to.
+    ///
+    /// The socket is bound in a new directory only accessible by the owning user and linked to
+    /// `path` once its permissions are restricted, so that no other user can connect before. The
+    /// link fails instead of replacing a file created at `path` in the meantime.
+    #[cfg(unix)]
+    fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+        let name = path.file_name().ok_or_else(|| {
+            let msg = format!("{} is not a socket path", path.display());
+            io::Error::new(io::ErrorKind::InvalidInput, msg)
+        })?;
+        let dir = path.with_file_name(format!(
+            ".{}.{}",
+            name.to_string_lossy(),
+            std::process::id()
+        ));
+        std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+        let tmp = dir.join("bsp.sock");
+        let bind = || -> io::Result<UnixListener> {
+            let listener = UnixListener::bind(&tmp)?;
+            std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
+            match std::fs::hard_link(&tmp, path) {
+                Ok(()) => Ok(listener),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    let is_socket = std::fs::symlink_metadata(path)
+                        .is_ok_and(|meta| meta.file_type().is_socket());
+                    let (kind, msg) = match is_socket {
+                        true => (io::ErrorKind::AddrInUse, "is used by another server"),
+                        false => (io::ErrorKind::AlreadyExists, "already exists"),
+                    };
+                    Err(io::Error::new(kind, format!("{} {}", path.display(), msg)))
+                }
+                Err(err) => Err(err),
+            }
+        };
+        let listener = bind();
+        let _ = std::fs::remove_file(&tmp);
+        std::fs::remove_dir(&dir)?;
+        listener
+    }
+
+    /// Spawn a build server with `command` (usually the `argv` of a connection file) and
+    /// connect to it over its stdin/stdout. The server's stderr is forwarded to the log.
+    ///
+    /// The returned [`ServerProcess`] can be used to notice when the server exits, kill it or
+    /// restart it.
+    pub fn spawn(command: Command) -> io::Result<(Connection, IoThreads, ServerProcess)> {
+        ServerProcess::spawn(command)
+    }
+
+    /// Creates a pair of connected connections in memory for testing: the server's side, then
+    /// the client's.
     pub fn memory() -> (Connection, Connection) {
         let ((s1, r1), (s2, r2)) = (unbounded(), unbounded());
-        (
-            Connection {
-                sender: s1,
-                receiver: r2,
-            },
-            Connection {
-                sender: s2,
-                receiver: r1,
-            },
-        )
+        let server = Connection::new(s1, r2);
+        server.track_lifecycle();
+        (server, Connection::new(s2, r1))
     }
 
     /// Initialize the connection. Sends the server initialize response
@@ -85,12 +273,16 @@ impl Connection {
     /// on success. If more fine-grained initialization is required use
     /// `initialize_start`/`initialize_finish`.
     ///
+    /// The BSP version is negotiated from the `bspVersion` of the client and of the result, see
+    /// [`Connection::bsp_version`]. A client talking an incompatible version is answered with the
+    /// server's version.
+    ///
     /// # Example
     ///
     /// ```no_run
     /// use std::error::Error;
-    /// use build_server_protocol::types::InitializeBuildResult;
-    /// use build_server_protocol::server::{Connection, Message, Request, RequestId, Response};
+    /// use bsp_server::types::{InitializeBuildResult, ServerCapabilities};
+    /// use bsp_server::Connection;
     ///
     /// fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
     ///    // Create the transport
@@ -98,11 +290,11 @@ impl Connection {
     ///
     ///    // Run the server
     ///    conn.initialize(|_params| {
-    ///        Ok(InitializeBuildResult::new_simple(
+    ///        InitializeBuildResult::new_simple(
     ///         "MyBuildServer",
     ///         "0.1",
     ///         "2.0",
-    ///         ServerCapabilities::default()))
+    ///         ServerCapabilities::default())
     ///    })?;
     ///
     ///    // ... Run main loop ...
@@ -115,115 +307,575 @@ impl Connection {
         &self,
         process: impl FnOnce(&InitializeBuild) -> V,
     ) -> Result<InitializeBuild, ProtocolError> {
-        let (id, params) = self.initialize_start()?;
-        self.initialize_finish(id, process(&params))?;
+        let exited = || ProtocolError("client exited during initialization".into());
+        let (id, params) = self.initialize_start()?.ok_or_else(exited)?;
+        if !self.initialize_finish(id, params.bsp_version(), process(&params))? {
+            return Err(exited());
+        }
         Ok(params)
     }
 
+    /// Wait for the initialize request, returning `None` if the client exits instead.
     #[tracing::instrument(skip(self))]
-    fn initialize_start(&self) -> Result<(RequestId, InitializeBuild), ProtocolError> {
+    fn initialize_start(&self) -> Result<Option<(RequestId, InitializeBuild)>, ProtocolError> {
+        self.track_lifecycle();
         loop {
-            match self.receiver.recv() {
-                Ok(Message::Request(Request::InitializeBuild(id, params))) => {
-                    return Ok((id, params));
+            match self.recv_from(&self.receiver, None)? {
+                Message::Request(Request::InitializeBuild(id, params)) => {
+                    let language_ids = params.capabilities().language_ids().to_vec();
+                    self.languages
+                        .lock()
+                        .unwrap()
+                        .set_language_ids(language_ids);
+                    return Ok(Some((id, params)));
                 }
-                // Respond to non-initialize requests with ServerNotInitialized
-                Ok(Message::Request(req)) => {
-                    let msg = format!("expected initialize request, got {:?}", req);
-                    tracing::error!("{}", msg);
-                    self.sender
-                        .send(Response::server_not_initialized(req.id().clone(), msg).into())
-                        .unwrap();
+                Message::Notification(Notification::Exit) => return Ok(None),
+                msg => tracing::warn!("expected initialize request, got {:?}", msg),
+            }
+        }
+    }
+
+    /// Finishes the initialization process by sending an `InitializeResult` to the client.
+    /// Returns `false` if the client exits instead of sending `build/initialized`.
+    ///
+    /// The BSP version is negotiated between the one the client asked for and the one of the
+    /// result, which is replaced with the outcome. If no version fits, the result keeps the
+    /// server's version and the client decides whether to go on.
+    #[tracing::instrument(skip_all)]
+    fn initialize_finish<V: Serialize>(
+        &self,
+        initialize_id: RequestId,
+        client_version: &str,
+        initialize_result: V,
+    ) -> Result<bool, ProtocolError> {
+        let mut resp = Response::ok(initialize_id.clone(), initialize_result);
+        let result = resp.result.as_mut().filter(|result| result.is_object());
+        let server_version = result
+            .as_ref()
+            .and_then(|result| result["bspVersion"].as_str())
+            .unwrap_or_default();
+        match BspVersion::negotiate(client_version, server_version) {
+            Ok(version) => {
+                if let Some(result) = result {
+                    result["bspVersion"] = version.to_string().into();
                 }
-                Ok(msg) => {
-                    let msg = format!("expected initialize request, got {:?}", msg);
-                    tracing::error!("{}", msg);
-                    return Err(ProtocolError(msg));
+                self.set_bsp_version(version);
+            }
+            Err(err) => {
+                tracing::warn!("{}, answering with the server's BSP version", err);
+                if let Ok(version) = server_version.parse() {
+                    self.set_bsp_version(version);
+                }
+            }
+        }
+        if self.check_capabilities {
+            let capabilities = resp.result.as_ref().and_then(|r| r.get("capabilities"));
+            match capabilities.map(ServerCapabilities::deserialize) {
+                Some(Ok(capabilities)) => {
+                    if let Some(lifecycle) = self.lifecycle.lock().unwrap().as_mut() {
+                        lifecycle.set_capabilities(capabilities);
+                    }
                 }
+                _ => tracing::warn!("initialize result has no capabilities, not checking requests"),
+            }
+        }
+        self.sender
+            .send(resp.into())
+            .map_err(|e| ProtocolError(e.to_string()))?;
+        loop {
+            match self.recv_from(&self.receiver, None)? {
+                Message::Notification(Notification::Initialized) => return Ok(true),
+                Message::Notification(Notification::Exit) => return Ok(false),
+                msg => tracing::warn!("expected initialized notification, got {:?}", msg),
+            }
+        }
+    }
+
+    /// The capabilities the server advertised in its initialize response, once it was sent.
+    pub fn capabilities(&self) -> Option<ServerCapabilities> {
+        let lifecycle = self.lifecycle.lock().unwrap();
+        lifecycle.as_ref()?.capabilities().cloned()
+    }
+
+    /// The BSP version negotiated during initialization, see [`BspVersion::negotiate`].
+    pub fn bsp_version(&self) -> Option<BspVersion> {
+        *self.bsp_version.lock().unwrap()
+    }
+
+    /// Set the BSP version talked on the connection. Clients set the version of the
+    /// initialize result, servers negotiate it in [`Connection::initialize`].
+    pub fn set_bsp_version(&self, version: BspVersion) {
+        *self.bsp_version.lock().unwrap() = Some(version);
+    }
+
+    /// Whether requests for features the server did not advertise are answered with
+    /// `MethodNotFound` instead of being handed to the server. Enabled by default.
+    pub fn check_capabilities(&self) -> bool {
+        self.check_capabilities
+    }
+
+    /// Set whether requests for unadvertised features are rejected. Has to be set before the
+    /// connection is initialized.
+    pub fn set_check_capabilities(&mut self, check_capabilities: bool) {
+        self.check_capabilities = check_capabilities;
+    }
+
+    /// The current state of the connection. Connections to a server do not track it and stay
+    /// [`LifecycleState::Uninitialized`].
+    pub fn state(&self) -> LifecycleState {
+        let lifecycle = self.lifecycle.lock().unwrap();
+        lifecycle
+            .as_ref()
+            .map(|lifecycle| lifecycle.state())
+            .unwrap_or_default()
+    }
+
+    /// The code the server process should exit with once the connection is done: `0` if the
+    /// client sent `build/exit` after `build/shutdown`, `1` otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # struct Server;
+    /// # impl bsp_server::types::BuildServer for Server {
+    /// #     fn initialize(
+    /// #         &self,
+    /// #         _params: bsp_server::types::InitializeBuild,
+    /// #     ) -> anyhow::Result<bsp_server::types::InitializeBuildResult> {
+    /// #         unimplemented!()
+    /// #     }
+    /// # }
+    /// let (conn, io_threads) = bsp_server::Connection::stdio();
+    /// conn.serve(Server).unwrap();
+    /// io_threads.join().unwrap();
+    /// std::process::exit(conn.exit_code());
+    /// ```
+    pub fn exit_code(&self) -> i32 {
+        let lifecycle = self.lifecycle.lock().unwrap();
+        lifecycle
+            .as_ref()
+            .map_or(1, |lifecycle| lifecycle.exit_code())
+    }
+
+    /// Record that the client went away.
+    fn exit_lifecycle(&self) {
+        if let Some(lifecycle) = self.lifecycle.lock().unwrap().as_mut() {
+            lifecycle.exit(false);
+        }
+    }
+
+    /// Receive the next message allowed in the current state, answering rejected requests and
+    /// dropping ignored messages.
+    ///
+    /// This applies the lifecycle and capability checks of [`Connection::serve`] to a loop
+    /// handling messages itself, which reading [`Connection::receiver`] directly skips. Fails once
+    /// the connection is closed.
+    pub fn recv(&self) -> Result<Message, ProtocolError> {
+        self.recv_from(&self.receiver, None)
+    }
+
+    /// Like [`Connection::recv`], reading from `receiver` and failing if no message arrives
+    /// within `timeout`.
+    fn recv_from(
+        &self,
+        receiver: &Receiver<Message>,
+        timeout: Option<Duration>,
+    ) -> Result<Message, ProtocolError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            if let Some(msg) = self.recv_until(receiver, deadline)? {
+                return Ok(msg);
+            }
+            if deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+                self.exit_lifecycle();
+                let msg = "failed to receive message: timed out".to_string();
+                tracing::error!("{}", msg);
+                return Err(ProtocolError(msg));
+            }
+        }
+    }
+
+    /// Like [`Connection::recv_from`], returning `None` once `deadline` passes or when
+    /// [`Connection::send_request`] sent a request whose deadline may be earlier.
+    fn recv_until(
+        &self,
+        receiver: &Receiver<Message>,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Message>, ProtocolError> {
+        loop {
+            let mut select = Select::new();
+            let message = select.recv(receiver);
+            select.recv(&self.wake.1);
+            let oper = match deadline {
+                // A closed channel is ready, so this only fails once the deadline passes.
+                Some(deadline) => match select.select_deadline(deadline) {
+                    Ok(oper) => oper,
+                    Err(SelectTimeoutError) => return Ok(None),
+                },
+                None => select.select(),
+            };
+            if oper.index() != message {
+                let _ = oper.recv(&self.wake.1);
+                return Ok(None);
+            }
+            let msg = match oper.recv(receiver) {
+                Ok(msg) => msg,
                 Err(e) => {
-                    let msg = format!("expected initialize request, got error: {}", e);
+                    self.exit_lifecycle();
+                    let msg = format!("failed to receive message: {}", e);
                     tracing::error!("{}", msg);
                     return Err(ProtocolError(msg));
                 }
             };
+            let verdict = match self.lifecycle.lock().unwrap().as_mut() {
+                Some(lifecycle) => lifecycle.advance(&msg),
+                None => lifecycle::Verdict::Accept,
+            };
+            match verdict {
+                lifecycle::Verdict::Accept => return Ok(Some(msg)),
+                lifecycle::Verdict::Reject(resp) => {
+                    tracing::warn!("rejecting {:?}", msg);
+                    if let Message::Request(req) = &msg {
+                        self.pending.lock().unwrap().complete(req.id().clone());
+                    }
+                    let _ = self.sender.send(resp.into());
+                }
+                lifecycle::Verdict::Ignore => tracing::debug!("ignoring {:?}", msg),
+            }
         }
     }
 
-    /// Finishes the initialization process by sending an `InitializeResult` to the client
-    #[tracing::instrument(skip_all)]
-    fn initialize_finish<V: Serialize>(
+    /// Send a request to the client, returning a handle to wait for its result.
+    ///
+    /// The response is handed to the returned [`PendingResponse`] by [`Connection::serve`] and
+    /// [`Connection::serve_concurrent`]. Only handlers run by [`Connection::serve_concurrent`]
+    /// can wait for it: [`Connection::serve`] reads the response on the thread running the
+    /// handler, so waiting there blocks until the request times out. Requests left unanswered
+    /// past the timeout set with [`Connection::set_request_timeout`] are cancelled with
+    /// `$/cancelRequest`.
+    pub fn send_request<R: BspRequest>(
         &self,
-        initialize_id: RequestId,
-        initialize_result: V,
-    ) -> Result<(), ProtocolError> {
-        let resp = Response::ok(initialize_id, initialize_result);
-        self.sender.send(resp.into()).unwrap();
-        match &self.receiver.recv() {
-            Ok(Message::Notification(Notification::Initialized)) => (),
-            Ok(msg) => {
-                let msg = format!("expected Message::Notification, got: {:?}", msg,);
-                tracing::error!("{}", msg);
-                return Err(ProtocolError(msg));
+        params: R::Params,
+    ) -> Result<PendingResponse<R>, ProtocolError> {
+        let (sender, receiver) = bounded(1);
+        let req = self
+            .outgoing
+            .lock()
+            .unwrap()
+            .register(R::METHOD, params, sender)
+            .map_err(|e| ProtocolError(e.to_string()))?;
+        let id = req.id().clone();
+        if let Err(e) = self.send(req) {
+            self.outgoing.lock().unwrap().complete(id);
+            return Err(ProtocolError(e.to_string()));
+        }
+        let _ = self.wake.0.try_send(());
+        Ok(PendingResponse::new(id, receiver))
+    }
+
+    /// Set how long requests sent with [`Connection::send_request`] from now on may wait for
+    /// their response. `None` (the default) waits forever.
+    pub fn set_request_timeout(&self, timeout: Option<Duration>) {
+        self.outgoing.lock().unwrap().set_timeout(timeout);
+    }
+
+    /// Hand a response to the [`PendingResponse`] of the request it answers.
+    fn complete_request(&self, resp: Response) {
+        let pending = resp
+            .id
+            .clone()
+            .and_then(|id| self.outgoing.lock().unwrap().complete(id));
+        match pending {
+            Some(sender) => {
+                let _ = sender.send(resp);
             }
-            Err(e) => {
-                let msg = format!("expected initialized notification, got error: {}", e,);
+            None => tracing::warn!("ignoring response to unknown request: {:?}", resp),
+        }
+    }
+
+    /// Cancel the requests sent to the client whose timeout elapsed.
+    fn expire_requests(&self) {
+        let expired = self.outgoing.lock().unwrap().expire(Instant::now());
+        for (id, sender) in expired {
+            tracing::warn!("request {} timed out", id);
+            let _ = self
+                .sender
+                .send(Notification::CancelRequest(id.clone()).into());
+            let msg = "no response within the timeout".to_string();
+            let _ = sender.send(Response::request_canceled(id, msg));
+        }
+    }
+
+    /// Drive a [`BuildServer`] implementation until the client shuts the server down.
+    ///
+    /// Performs the initialize handshake using [`BuildServer::initialize`], then routes every
+    /// incoming request to the matching trait method and sends back its result, answering
+    /// requests whose handler panics with `InternalError`. Returns once `build/exit` is received,
+    /// and fails if the client disconnects before.
+    ///
+    /// Requests cancelled with `$/cancelRequest` have their [`types::CancellationToken`] cancelled
+    /// and are answered with `RequestCanceled`, see [`types::CancellationToken`] for tracking tasks.
+    ///
+    /// Implementations declared with [`types::build_server!`] advertise capabilities derived from
+    /// the methods they override, with language based providers defaulting to the languages the
+    /// client declared.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use bsp_server::types::*;
+    /// use bsp_server::Connection;
+    ///
+    /// struct Server;
+    ///
+    /// impl BuildServer for Server {
+    ///     fn initialize(&self, _params: InitializeBuild) -> anyhow::Result<InitializeBuildResult> {
+    ///         Ok(InitializeBuildResult::new_simple(
+    ///             "MyBuildServer",
+    ///             "0.1",
+    ///             "2.0",
+    ///             ServerCapabilities::default(),
+    ///         ))
+    ///     }
+    /// }
+    ///
+    /// fn main() -> anyhow::Result<()> {
+    ///     let (conn, io_threads) = Connection::stdio();
+    ///     conn.serve(Server)?;
+    ///     io_threads.join()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[tracing::instrument(skip_all)]
+    pub fn serve<S: BuildServer>(&self, server: S) -> Result<(), ProtocolError> {
+        if !self.serve_initialize(&server)? {
+            return Ok(());
+        }
+        self.serve_requests(
+            &server,
+            |req, pending| dispatch::handle(&server, req, pending, &self.languages, &self.sender),
+            || (),
+        )
+    }
+
+    /// Like [`Connection::serve`], but runs request handlers on a pool of `workers` threads, so
+    /// that a long `buildTarget/test` does not hold up queries such as `workspace/buildTargets`.
+    ///
+    /// Requests changing the build state (`workspace/reload`, `buildTarget/cleanCache` and
+    /// `workspace/setCargoFeatures`) wait for all in-flight requests to finish and run alone, and
+    /// requests received after them only start once they are done. `build/shutdown` waits for
+    /// every in-flight request to be answered before it is acknowledged.
+    #[tracing::instrument(skip_all)]
+    pub fn serve_concurrent<S>(&self, server: S, workers: usize) -> Result<(), ProtocolError>
+    where
+        S: BuildServer + Send + Sync + 'static,
+    {
+        if !self.serve_initialize(&server)? {
+            return Ok(());
+        }
+        let server = Arc::new(server);
+        let pool = pool::WorkerPool::new(workers);
+        self.serve_requests(
+            &*server,
+            |req, pending| {
+                if dispatch::is_exclusive(&req) {
+                    pool.wait_idle();
+                    return dispatch::handle(&*server, req, pending, &self.languages, &self.sender);
+                }
+                let (server, pending, sender) =
+                    (server.clone(), pending.clone(), self.sender.clone());
+                let languages = self.languages.clone();
+                pool.execute(move || {
+                    let id = req.id().clone();
+                    if let Err(err) = dispatch::handle(&*server, req, &pending, &languages, &sender)
+                    {
+                        tracing::error!("failed to answer request {}: {}", id, err);
+                    }
+                });
+                Ok(())
+            },
+            || pool.wait_idle(),
+        )
+    }
+
+    /// Initialize the connection using [`BuildServer::initialize`]. Returns `false` if the
+    /// client exited before initialization completed.
+    fn serve_initialize<S: BuildServer + ?Sized>(&self, server: &S) -> Result<bool, ProtocolError> {
+        let Some((id, params)) = self.initialize_start()? else {
+            server.on_build_exit();
+            return Ok(false);
+        };
+        let language_ids = params.capabilities().language_ids().to_vec();
+        let client_version = params.bsp_version().to_string();
+        match server.initialize(params) {
+            Ok(mut result) => {
+                if let Some(methods) = server.implemented_methods() {
+                    result
+                        .capabilities_mut()
+                        .derive_from_methods(methods, &language_ids);
+                }
+                if !self.initialize_finish(id, &client_version, result)? {
+                    server.on_build_exit();
+                    return Ok(false);
+                }
+            }
+            Err(err) => {
+                let msg = format!("failed to initialize: {:#}", err);
                 tracing::error!("{}", msg);
+                let _ = self
+                    .sender
+                    .send(Response::internal_error(id, msg.clone()).into());
                 return Err(ProtocolError(msg));
             }
         }
-        Ok(())
+        server.on_initializtion();
+        // `buildTarget/didChange` events do not carry the languages of their targets.
+        dispatch::refresh_languages(server, &self.languages);
+        Ok(true)
+    }
+
+    /// Pass every incoming request to `handle` until the client shuts the server down, calling
+    /// `drain` to wait for in-flight requests before acknowledging `build/shutdown`.
+    ///
+    /// Requests sent to the client that are still pending once this returns fail.
+    fn serve_requests<S: BuildServer + ?Sized>(
+        &self,
+        server: &S,
+        handle: impl FnMut(Request, &dispatch::Pending) -> Result<(), ProtocolError>,
+        drain: impl Fn(),
+    ) -> Result<(), ProtocolError> {
+        let pending = self.pending.clone();
+        let (receiver, watcher) =
+            dispatch::watch_cancellation(self.receiver.clone(), pending.clone());
+        let result = self.serve_messages(server, &receiver, &pending, handle, drain);
+        watcher.stop();
+        self.outgoing.lock().unwrap().clear();
+        result
+    }
+
+    fn serve_messages<S: BuildServer + ?Sized>(
+        &self,
+        server: &S,
+        receiver: &Receiver<Message>,
+        pending: &dispatch::Pending,
+        mut handle: impl FnMut(Request, &dispatch::Pending) -> Result<(), ProtocolError>,
+        drain: impl Fn(),
+    ) -> Result<(), ProtocolError> {
+        loop {
+            self.expire_requests();
+            let deadline = self.outgoing.lock().unwrap().next_deadline();
+            let msg = match self.recv_until(receiver, deadline) {
+                Ok(Some(msg)) => msg,
+                Ok(None) => continue,
+                Err(_) => return Err(ProtocolError("client disconnected before exit".to_string())),
+            };
+            match msg {
+                Message::Request(Request::Shutdown(id)) => {
+                    tracing::info!("processing shutdown server ...");
+                    drain();
+                    pending.lock().unwrap().complete(id.clone());
+                    let resp = dispatch::respond(id, server.build_shutdown());
+                    let _ = self.sender.send(resp.into());
+                    self.wait_for_exit(receiver)?;
+                    server.on_build_exit();
+                    return Ok(());
+                }
+                Message::Request(req) => handle(req, pending)?,
+                Message::Notification(Notification::Exit) => {
+                    tracing::warn!("received exit notification before shutdown");
+                    server.on_build_exit();
+                    return Ok(());
+                }
+                Message::Notification(notification) => {
+                    tracing::debug!("ignoring notification: {:?}", notification);
+                }
+                Message::Response(resp) => self.complete_request(resp),
+                Message::Invalid(invalid) => {
+                    tracing::warn!("skipping {}", invalid);
+                }
+            }
+        }
     }
 
-    /// If `req` is `Shutdown`, respond to it and return `true`, otherwise return `false`
+    /// If `req` is `Shutdown`, respond to it and return `true`, otherwise return `false`.
+    ///
+    /// After responding, requests are answered with `InvalidRequest` until `build/exit` arrives.
     pub fn handle_shutdown(&self, req: &Request) -> Result<bool, ProtocolError> {
         if let Request::Shutdown(id) = req {
             tracing::info!("processing shutdown server ...");
+            // Record the transition in case the request was read from `receiver` directly.
+            if let Some(lifecycle) = self.lifecycle.lock().unwrap().as_mut() {
+                let _ = lifecycle.advance(&req.clone().into());
+            }
             let resp = Response::ok(id.clone(), ());
             let _ = self.sender.send(resp.into());
-            match &self.receiver.recv_timeout(Duration::from_secs(30)) {
-                Ok(Message::Notification(Notification::Exit)) => (),
-                Ok(msg) => {
-                    let msg = format!("unexpected message during shutdown: {:?}", msg);
-                    tracing::error!("{}", msg);
-
-                    return Err(ProtocolError(msg));
-                }
-                Err(e) => {
-                    let msg = format!("unexpected error during shutdown: {}", e);
-                    return Err(ProtocolError(msg));
-                }
-            }
+            self.wait_for_exit(&self.receiver)?;
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
+    /// Wait for the client to send `build/exit` after a shutdown response.
+    fn wait_for_exit(&self, receiver: &Receiver<Message>) -> Result<(), ProtocolError> {
+        loop {
+            let msg = self.recv_from(receiver, Some(Duration::from_secs(30)))?;
+            if let Message::Notification(Notification::Exit) = msg {
+                return Ok(());
+            }
+        }
+    }
+
     /// delegates to self.sender
+    ///
+    /// `buildTarget/didChange` events about targets of languages the client did not declare
+    /// are dropped, and so is the notification if no event is left. The same applies to the
+    /// other `send_*` methods, but not when using `self.sender` directly.
+    #[allow(clippy::result_large_err)] // Hands the unsent message back, like `Sender`.
     pub fn send<T: Into<Message>>(&self, msg: T) -> Result<(), SendError<Message>> {
-        self.sender.send(msg.into())
+        match self.filter(msg.into()) {
+            Some(msg) => self.sender.send(msg),
+            None => Ok(()),
+        }
     }
 
     /// delegates to self.sender
+    #[allow(clippy::result_large_err)] // Hands the unsent message back, like `Sender`.
     pub fn try_send<T: Into<Message>>(&self, msg: T) -> Result<(), TrySendError<Message>> {
-        self.sender.try_send(msg.into())
+        match self.filter(msg.into()) {
+            Some(msg) => self.sender.try_send(msg),
+            None => Ok(()),
+        }
     }
 
     /// delegates to self.sender
+    #[allow(clippy::result_large_err)] // Hands the unsent message back, like `Sender`.
     pub fn send_timeout<T: Into<Message>>(
         &self,
         msg: T,
         timeout: Duration,
     ) -> Result<(), SendTimeoutError<Message>> {
-        self.sender.send_timeout(msg.into(), timeout)
+        match self.filter(msg.into()) {
+            Some(msg) => self.sender.send_timeout(msg, timeout),
+            None => Ok(()),
+        }
     }
 
     /// delegates to self.sender
+    #[allow(clippy::result_large_err)] // Hands the unsent message back, like `Sender`.
     pub fn send_deadline<T: Into<Message>>(
         &self,
         msg: T,
         deadline: Instant,
     ) -> Result<(), SendTimeoutError<Message>> {
-        self.sender.send_deadline(msg.into(), deadline)
+        match self.filter(msg.into()) {
+            Some(msg) => self.sender.send_deadline(msg, deadline),
+            None => Ok(()),
+        }
+    }
+
+    /// Apply the client's declared languages to an outgoing message.
+    fn filter(&self, msg: Message) -> Option<Message> {
+        self.languages.lock().unwrap().message(msg)
     }
 }
@@ -0,0 +1,242 @@
+use crate::{methods, Message, Notification, Request, Response};
+use bsp_types::ServerCapabilities;
+
+/// State of a connection as it moves through the BSP lifecycle, see [`crate::Connection::state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LifecycleState {
+    /// Waiting for `build/initialize`. Other requests are answered with `ServerNotInitialized`
+    /// and notifications other than `build/exit` are dropped.
+    #[default]
+    Uninitialized,
+    /// `build/initialize` was received, waiting for `build/initialized`.
+    Initializing,
+    /// Handling requests.
+    Running,
+    /// `build/shutdown` was received. Further requests are answered with `InvalidRequest`
+    /// until `build/exit` arrives.
+    ShuttingDown,
+    /// `build/exit` was received or the client went away.
+    Exited,
+}
+
+/// What to do with a message received in the current state.
+#[derive(Debug)]
+pub(crate) enum Verdict {
+    /// Process the message.
+    Accept,
+    /// Answer the request with the given error instead of processing it.
+    Reject(Response),
+    /// Drop the message.
+    Ignore,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Lifecycle {
+    state: LifecycleState,
+    exit_code: Option<i32>,
+    /// Capabilities sent in the initialize response, requests for other features are rejected.
+    capabilities: Option<ServerCapabilities>,
+}
+
+impl Lifecycle {
+    pub(crate) fn state(&self) -> LifecycleState {
+        self.state
+    }
+
+    /// The exit code the server process should use: `0` if `build/exit` followed
+    /// `build/shutdown`, `1` otherwise.
+    pub(crate) fn exit_code(&self) -> i32 {
+        self.exit_code.unwrap_or(1)
+    }
+
+    pub(crate) fn capabilities(&self) -> Option<&ServerCapabilities> {
+        self.capabilities.as_ref()
+    }
+
+    /// Reject requests for features `capabilities` does not advertise from now on.
+    pub(crate) fn set_capabilities(&mut self, capabilities: ServerCapabilities) {
+        self.capabilities = Some(capabilities);
+    }
+
+    /// Decide what to do with `msg`, moving to the next state if `msg` triggers a transition.
+    pub(crate) fn advance(&mut self, msg: &Message) -> Verdict {
+        use LifecycleState::*;
+        match (self.state, msg) {
+            (Exited, _) => Verdict::Ignore,
+            (state, Message::Notification(Notification::Exit)) => {
+                self.exit(state == ShuttingDown);
+                Verdict::Accept
+            }
+            (Uninitialized, Message::Request(Request::InitializeBuild(..))) => {
+                self.state = Initializing;
+                Verdict::Accept
+            }
+            (_, Message::Request(Request::InitializeBuild(id, _))) => {
+                let msg = "server is already initialized".to_string();
+                Verdict::Reject(Response::invalid_request(id.clone(), msg))
+            }
+            (Uninitialized | Initializing, Message::Request(req)) => {
+                let msg = format!("expected initialize request, got {}", req.method());
+                Verdict::Reject(Response::server_not_initialized(req.id().clone(), msg))
+            }
+            (Initializing, Message::Notification(Notification::Initialized)) => {
+                self.state = Running;
+                Verdict::Accept
+            }
+            (Running, Message::Request(Request::Shutdown(_))) => {
+                self.state = ShuttingDown;
+                Verdict::Accept
+            }
+            (Running, Message::Request(req)) if !self.advertises(req) => {
+                let msg = format!("server does not support {}", req.method());
+                Verdict::Reject(Response::method_not_found(req.id().clone(), msg))
+            }
+            (Running, Message::Notification(notification))
+                if methods::sent_by_server(notification.method()) =>
+            {
+                Verdict::Ignore
+            }
+            (Running, _) => Verdict::Accept,
+            (ShuttingDown, Message::Request(req)) => {
+                let msg = format!("server is shutting down, got {}", req.method());
+                Verdict::Reject(Response::invalid_request(req.id().clone(), msg))
+            }
+            (Uninitialized | Initializing | ShuttingDown, _) => Verdict::Ignore,
+        }
+    }
+
+    /// Returns `false` if `req` asks for a feature left out of the negotiated capabilities.
+    fn advertises(&self, req: &Request) -> bool {
+        let Some(caps) = &self.capabilities else {
+            return true;
+        };
+        let flag = |value: Option<bool>| value.unwrap_or(false);
+        match req {
+            Request::BuildTargetCompile(..) => caps.compile_provider().is_some(),
+            Request::BuildTargetTest(..) => caps.test_provider().is_some(),
+            Request::BuildTargetRun(..) => caps.run_provider().is_some(),
+            Request::DebugSessionStart(..) => caps.debug_provider().is_some(),
+            Request::TextDocumentInverseSources(..) => flag(caps.inverse_sources_provider()),
+            Request::BuildTargetDependencySources(..) => flag(caps.dependency_sources_provider()),
+            Request::BuildTargetDependencyModules(..) => flag(caps.dependency_modules_provider()),
+            Request::BuildTargetResources(..) => flag(caps.resources_provider()),
+            Request::WorkspaceReload(_) => flag(caps.can_reload()),
+            Request::BuildTargetJvmRunEnvironment(..) => flag(caps.jvm_run_environment_provider()),
+            Request::BuildTargetJvmTestEnvironment(..) => {
+                flag(caps.jvm_test_environment_provider())
+            }
+            Request::BuildTargetJvmCompileClasspath(..) => {
+                flag(caps.jvm_compile_classpath_provider())
+            }
+            Request::WorkspaceCargoFeaturesState(_) | Request::WorkspaceSetCargoFeatures(..) => {
+                flag(caps.cargo_features_provider())
+            }
+            _ => true,
+        }
+    }
+
+    /// Move to [`LifecycleState::Exited`], exiting cleanly only if the server was shut down.
+    pub(crate) fn exit(&mut self, clean: bool) {
+        if self.state != LifecycleState::Exited {
+            self.state = LifecycleState::Exited;
+            self.exit_code = Some(if clean { 0 } else { 1 });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorCode;
+
+    fn rejected_with(verdict: Verdict) -> i32 {
+        match verdict {
+            Verdict::Reject(resp) => resp.error.unwrap().code,
+            verdict => panic!("expected rejection, got {:?}", verdict),
+        }
+    }
+
+    #[test]
+    fn clean_exit() {
+        let mut lifecycle = Lifecycle::default();
+        let shutdown = Message::from(Request::Shutdown(2.into()));
+        let verdict = lifecycle.advance(&shutdown);
+        assert_eq!(
+            rejected_with(verdict),
+            ErrorCode::ServerNotInitialized as i32
+        );
+        let initialized = Message::from(Notification::Initialized);
+        assert!(matches!(lifecycle.advance(&initialized), Verdict::Ignore));
+
+        let params = serde_json::from_value(serde_json::json!({
+            "displayName": "client",
+            "rootUri": "file:///tmp",
+        }))
+        .unwrap();
+        let initialize = Message::from(Request::InitializeBuild(1.into(), params));
+        assert!(matches!(lifecycle.advance(&initialize), Verdict::Accept));
+        assert_eq!(lifecycle.state(), LifecycleState::Initializing);
+        assert!(matches!(lifecycle.advance(&initialized), Verdict::Accept));
+        assert_eq!(lifecycle.state(), LifecycleState::Running);
+        let verdict = lifecycle.advance(&initialize);
+        assert_eq!(rejected_with(verdict), ErrorCode::InvalidRequest as i32);
+
+        assert!(matches!(lifecycle.advance(&shutdown), Verdict::Accept));
+        assert_eq!(lifecycle.state(), LifecycleState::ShuttingDown);
+        let verdict = lifecycle.advance(&Request::WorkspaceBuildTargets(3.into()).into());
+        assert_eq!(rejected_with(verdict), ErrorCode::InvalidRequest as i32);
+
+        assert!(matches!(
+            lifecycle.advance(&Notification::Exit.into()),
+            Verdict::Accept
+        ));
+        assert_eq!(lifecycle.state(), LifecycleState::Exited);
+        assert_eq!(lifecycle.exit_code(), 0);
+    }
+
+    #[test]
+    fn unadvertised_capabilities() {
+        let mut lifecycle = Lifecycle {
+            state: LifecycleState::Running,
+            ..Default::default()
+        };
+        let reload = Message::from(Request::WorkspaceReload(1.into()));
+        assert!(matches!(lifecycle.advance(&reload), Verdict::Accept));
+
+        let mut capabilities = ServerCapabilities::default();
+        capabilities.set_can_reload(Some(false));
+        lifecycle.set_capabilities(capabilities);
+        let verdict = lifecycle.advance(&reload);
+        assert_eq!(rejected_with(verdict), ErrorCode::MethodNotFound as i32);
+        let targets = Message::from(Request::WorkspaceBuildTargets(2.into()));
+        assert!(matches!(lifecycle.advance(&targets), Verdict::Accept));
+
+        let classpath = bsp_types::JvmCompileClasspath::new(vec![]);
+        let classpath = Message::from(Request::BuildTargetJvmCompileClasspath(3.into(), classpath));
+        let verdict = lifecycle.advance(&classpath);
+        assert_eq!(rejected_with(verdict), ErrorCode::MethodNotFound as i32);
+    }
+
+    #[test]
+    fn notifications_from_the_wrong_side() {
+        let mut lifecycle = Lifecycle {
+            state: LifecycleState::Running,
+            ..Default::default()
+        };
+        let log = Message::from(Notification::LogMessage(Default::default()));
+        assert!(matches!(lifecycle.advance(&log), Verdict::Ignore));
+        let cancel = Message::from(Notification::CancelRequest(1.into()));
+        assert!(matches!(lifecycle.advance(&cancel), Verdict::Accept));
+    }
+
+    #[test]
+    fn exit_without_shutdown() {
+        let mut lifecycle = Lifecycle::default();
+        assert!(matches!(
+            lifecycle.advance(&Notification::Exit.into()),
+            Verdict::Accept
+        ));
+        assert_eq!(lifecycle.state(), LifecycleState::Exited);
+        assert_eq!(lifecycle.exit_code(), 1);
+    }
+}
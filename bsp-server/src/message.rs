@@ -1,26 +1,83 @@
+use crate::framing::{FrameReader, FrameWriter};
 use crate::Notification;
 use crate::Request;
-use crate::Response;
-use serde::{Deserialize, Serialize};
+use crate::{ErrorCode, InvalidMessage, RequestId};
+use crate::{Response, ResponseError};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use serde_json::{value::RawValue, Value};
 use std::io;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum Message {
     Request(Request),
     Response(Response),
     Notification(Notification),
+    /// A message that could not be decoded. Only produced when reading, never sent.
+    #[serde(skip)]
+    Invalid(InvalidMessage),
+}
+
+impl<'de> Deserialize<'de> for Message {
+    /// Tell messages apart by their members, so that `params` is parsed only once, straight into
+    /// the type expected by the method.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Parts {
+            #[serde(default, deserialize_with = "present")]
+            id: Option<Option<RequestId>>,
+            method: Option<String>,
+            #[serde(default, deserialize_with = "present")]
+            params: Option<Box<RawValue>>,
+            result: Option<Value>,
+            error: Option<ResponseError>,
+        }
+
+        let Parts {
+            id,
+            method,
+            params,
+            result,
+            error,
+        } = Parts::deserialize(deserializer)?;
+        let msg = match (method, id) {
+            (Some(method), Some(Some(id))) => {
+                Request::from_parts(id, method, params).map(Message::Request)
+            }
+            (Some(method), None) => {
+                Notification::from_parts(method, params).map(Message::Notification)
+            }
+            (Some(_), Some(None)) => Err(serde_json::Error::custom("request id must not be null")),
+            (None, Some(id)) => Ok(Message::Response(Response { id, result, error })),
+            (None, None) => Err(serde_json::Error::custom(
+                "data did not match any variant of Message",
+            )),
+        };
+        msg.map_err(D::Error::custom)
+    }
+}
+
+/// Deserialize a member that may be `null`, telling it apart from a missing one.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
 }
 
 #[derive(Serialize)]
-struct JsonRpc {
+struct JsonRpc<'a> {
     jsonrpc: &'static str,
     #[serde(flatten)]
-    msg: Message,
+    msg: &'a Message,
 }
 
-impl From<Message> for JsonRpc {
-    fn from(msg: Message) -> Self {
+impl<'a> From<&'a Message> for JsonRpc<'a> {
+    fn from(msg: &'a Message) -> Self {
         Self {
             jsonrpc: "2.0",
             msg,
@@ -29,63 +86,80 @@ impl From<Message> for JsonRpc {
 }
 
 impl Message {
-    pub(crate) fn read(r: &mut dyn io::BufRead) -> io::Result<Option<Message>> {
-        let text = match read_msg_text(r)? {
-            Some(text) => text,
-            None => return Ok(None),
+    /// Read the next message, returning `None` at end of input.
+    ///
+    /// Malformed frames and undecodable messages are consumed and returned as
+    /// [`Message::Invalid`], only IO failures are returned as errors.
+    pub(crate) fn read<R: io::BufRead>(r: &mut FrameReader<R>) -> io::Result<Option<Message>> {
+        let text = match r.read_frame() {
+            Ok(Some(text)) => text,
+            Ok(None) => return Ok(None),
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                let invalid = InvalidMessage::new(ErrorCode::ParseError, None, err, true);
+                return Ok(Some(Message::Invalid(invalid)));
+            }
+            Err(err) => return Err(err),
         };
-        let msg = serde_json::from_str(&text)?;
-        tracing::trace!("Got <<<<<<<<<<<<<<<<<<<<<<<<<\n\n{:#?}\n", msg);
+        let msg = Message::decode(&text);
+        if tracing::enabled!(tracing::Level::TRACE) {
+            tracing::trace!("Got <<<<<<<<<<<<<<<<<<<<<<<<<\n\n{:#?}\n", msg);
+        }
         Ok(Some(msg))
     }
 
-    pub(crate) fn write(self, w: &mut dyn io::Write) -> io::Result<()> {
-        tracing::trace!("Sent >>>>>>>>>>>>>>>>>>>>>>>>>\n\n{:#?}\n", self);
-        let msg = serde_json::to_string(&JsonRpc::from(self))?;
-        write!(w, "Content-Length: {}\r\n\r\n", msg.len())?;
-        w.write_all(msg.as_bytes())?;
-        w.flush()?;
-        Ok(())
+    /// Decode a message body, classifying why it is invalid if it can't be decoded.
+    fn decode(text: &str) -> Message {
+        let err = match serde_json::from_str(text) {
+            Ok(msg) => return msg,
+            Err(err) => err,
+        };
+        let value: Value = match serde_json::from_str(text) {
+            Ok(value) => value,
+            Err(_) => {
+                return Message::Invalid(InvalidMessage::new(
+                    ErrorCode::ParseError,
+                    None,
+                    err,
+                    true,
+                ))
+            }
+        };
+        let id = value
+            .get("id")
+            .and_then(|id| RequestId::deserialize(id).ok());
+        let invalid = match (value.get("method"), value.get("id")) {
+            (Some(_), Some(_)) if id.is_some() => {
+                let err = Request::deserialize(value).err().map(|e| e.to_string());
+                let err = err.unwrap_or_else(|| err_string(text));
+                InvalidMessage::new(ErrorCode::InvalidParams, id, err, true)
+            }
+            (Some(_), None) => {
+                let err = Notification::deserialize(value)
+                    .err()
+                    .map(|e| e.to_string());
+                let err = err.unwrap_or_else(|| err_string(text));
+                InvalidMessage::new(ErrorCode::InvalidParams, None, err, false)
+            }
+            (None, Some(_)) if value.get("result").or(value.get("error")).is_some() => {
+                InvalidMessage::new(ErrorCode::InvalidRequest, id, err, false)
+            }
+            _ => InvalidMessage::new(ErrorCode::InvalidRequest, id, err, true),
+        };
+        Message::Invalid(invalid)
     }
-}
-
-fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
-    io::Error::new(io::ErrorKind::InvalidData, error)
-}
-
-macro_rules! invalid_data { ($($tt:tt)*) => (invalid_data(format!($($tt)*))) }
-
-fn read_msg_text(inp: &mut dyn io::BufRead) -> io::Result<Option<String>> {
-    let mut size = None;
-    let mut buf = String::new();
 
-    loop {
-        buf.clear();
-        if inp.read_line(&mut buf)? == 0 {
-            return Ok(None);
+    pub(crate) fn write<W: io::Write>(&self, w: &mut FrameWriter<W>) -> io::Result<()> {
+        if let Message::Invalid(invalid) = self {
+            tracing::warn!("not sending {}", invalid);
+            return Ok(());
         }
-        if !buf.ends_with("\r\n") {
-            return Err(invalid_data!("malformed header: {:?}", buf));
-        }
-        let buf = &buf[..buf.len() - 2];
-        if buf.is_empty() {
-            break;
-        }
-        let mut parts = buf.splitn(2, ": ");
-        let header_name = parts.next().unwrap();
-        let header_value = parts
-            .next()
-            .ok_or_else(|| invalid_data!("malformed header: {:?}", buf))?;
-        if header_name == "Content-Length" {
-            size = Some(header_value.parse::<usize>().map_err(invalid_data)?);
+        if tracing::enabled!(tracing::Level::TRACE) {
+            tracing::trace!("Sent >>>>>>>>>>>>>>>>>>>>>>>>>\n\n{:#?}\n", self);
         }
+        w.write_frame(|buf| serde_json::to_writer(buf, &JsonRpc::from(self)))
     }
+}
 
-    let size: usize = size.ok_or_else(|| invalid_data!("no Content-Length"))?;
-    let mut buf = buf.into_bytes();
-
-    buf.resize(size, 0);
-    inp.read_exact(&mut buf)?;
-
-    Ok(Some(String::from_utf8(buf).map_err(invalid_data)?))
+fn err_string(text: &str) -> String {
+    format!("invalid message: {text}")
 }
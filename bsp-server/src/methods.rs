@@ -0,0 +1,537 @@
+//! Typed descriptions of BSP methods.
+//!
+//! Each built-in method has a marker type implementing [`BspRequest`] or [`BspNotification`],
+//! named after the matching [`crate::Request`] or [`crate::Notification`] variant. Extensions can
+//! implement the traits for their own methods and read them with [`crate::Request::extract`],
+//! [`crate::Response::extract`] and [`crate::Notification::extract`].
+//!
+//! [`METHODS`] lists the built-in methods with who sends them and whether they take params.
+//! Messages are read and written following it, see [`lookup`].
+//!
+//! ```
+//! use bsp_server::methods::BspRequest;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct FormatParams {
+//!     file: String,
+//! }
+//!
+//! enum Format {}
+//!
+//! impl BspRequest for Format {
+//!     const METHOD: &'static str = "custom/format";
+//!     type Params = FormatParams;
+//!     type Result = Vec<String>;
+//! }
+//! ```
+use bsp_types as types;
+use serde::{
+    de::{DeserializeOwned, Error as DeError},
+    Deserialize, Serialize,
+};
+use serde_json::{value::RawValue, Value};
+
+use crate::{Notification, Request, RequestId};
+
+/// A request method, with the params it is sent with and the result it is answered with.
+pub trait BspRequest {
+    const METHOD: &'static str;
+    /// The side sending the request.
+    const DIRECTION: Direction = Direction::Both;
+    /// Whether `params` must be present. Requests that take no params are read with or without
+    /// them and written without them.
+    const PARAMS_REQUIRED: bool = true;
+    type Params: DeserializeOwned + Serialize;
+    type Result: DeserializeOwned + Serialize;
+}
+
+/// A notification method, with the params it is sent with.
+pub trait BspNotification {
+    const METHOD: &'static str;
+    /// The side sending the notification.
+    const DIRECTION: Direction = Direction::Both;
+    /// Whether `params` must be present. Notifications that take no params are read with or
+    /// without them and written without them.
+    const PARAMS_REQUIRED: bool = true;
+    type Params: DeserializeOwned + Serialize;
+}
+
+/// The side of the connection sending a method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ClientToServer,
+    ServerToClient,
+    Both,
+}
+
+/// Registry entry describing a method, see [`METHODS`].
+#[derive(Debug, Clone, Copy)]
+pub struct MethodInfo {
+    method: &'static str,
+    direction: Direction,
+    params_required: bool,
+    params_type: fn() -> &'static str,
+    read: Read,
+}
+
+/// Reads the params of a built-in method into the matching message variant.
+#[derive(Debug, Clone, Copy)]
+enum Read {
+    Request(fn(RequestId, &RawValue) -> serde_json::Result<Request>),
+    Notification(fn(&RawValue) -> serde_json::Result<Notification>),
+}
+
+impl MethodInfo {
+    /// Describe the built-in request `R`.
+    const fn request<R: BuiltinRequest>() -> Self {
+        Self {
+            method: R::METHOD,
+            direction: R::DIRECTION,
+            params_required: R::PARAMS_REQUIRED,
+            params_type: std::any::type_name::<R::Params>,
+            read: Read::Request(R::read),
+        }
+    }
+
+    /// Describe the built-in notification `N`.
+    const fn notification<N: BuiltinNotification>() -> Self {
+        Self {
+            method: N::METHOD,
+            direction: N::DIRECTION,
+            params_required: N::PARAMS_REQUIRED,
+            params_type: std::any::type_name::<N::Params>,
+            read: Read::Notification(N::read),
+        }
+    }
+
+    /// Get the method name.
+    pub fn method(&self) -> &'static str {
+        self.method
+    }
+
+    /// Returns `true` for requests, `false` for notifications.
+    pub fn is_request(&self) -> bool {
+        matches!(self.read, Read::Request(_))
+    }
+
+    /// Get the side sending the method.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Returns `true` if messages of this method must carry `params`.
+    pub fn params_required(&self) -> bool {
+        self.params_required
+    }
+
+    /// Get the name of the params type, for diagnostics.
+    pub fn params_type(&self) -> &'static str {
+        (self.params_type)()
+    }
+
+    /// Read a request of this method from its `params`.
+    pub(crate) fn read_request(
+        &self,
+        id: RequestId,
+        params: &RawValue,
+    ) -> serde_json::Result<Request> {
+        match self.read {
+            Read::Request(read) => read(id, params).map_err(|err| self.invalid_params(err)),
+            Read::Notification(_) => Err(DeError::custom(format_args!(
+                "{} is a notification",
+                self.method
+            ))),
+        }
+    }
+
+    /// Read a notification of this method from its `params`.
+    pub(crate) fn read_notification(&self, params: &RawValue) -> serde_json::Result<Notification> {
+        match self.read {
+            Read::Notification(read) => read(params).map_err(|err| self.invalid_params(err)),
+            Read::Request(_) => Err(DeError::custom(format_args!(
+                "{} is a request",
+                self.method
+            ))),
+        }
+    }
+
+    fn invalid_params(&self, err: serde_json::Error) -> serde_json::Error {
+        DeError::custom(format_args!(
+            "invalid params for {}, expected {}: {}",
+            self.method,
+            self.params_type(),
+            err
+        ))
+    }
+}
+
+/// Every built-in method.
+pub const METHODS: &[MethodInfo] = &[
+    MethodInfo::request::<InitializeBuild>(),
+    MethodInfo::request::<Shutdown>(),
+    MethodInfo::request::<WorkspaceBuildTargets>(),
+    MethodInfo::request::<WorkspaceReload>(),
+    MethodInfo::request::<BuildTargetDependencyModules>(),
+    MethodInfo::request::<DebugSessionStart>(),
+    MethodInfo::request::<BuildTargetSources>(),
+    MethodInfo::request::<TextDocumentInverseSources>(),
+    MethodInfo::request::<BuildTargetDependencySources>(),
+    MethodInfo::request::<BuildTargetResources>(),
+    MethodInfo::request::<BuildTargetRun>(),
+    MethodInfo::request::<BuildTargetCompile>(),
+    MethodInfo::request::<BuildTargetTest>(),
+    MethodInfo::request::<BuildTargetCleanCache>(),
+    MethodInfo::request::<BuildTargetJvmRunEnvironment>(),
+    MethodInfo::request::<BuildTargetJvmTestEnvironment>(),
+    MethodInfo::request::<BuildTargetJvmCompileClasspath>(),
+    MethodInfo::request::<BuildTargetRustWorkspace>(),
+    MethodInfo::request::<WorkspaceCargoFeaturesState>(),
+    MethodInfo::request::<WorkspaceSetCargoFeatures>(),
+    MethodInfo::notification::<Exit>(),
+    MethodInfo::notification::<Initialized>(),
+    MethodInfo::notification::<CancelRequest>(),
+    MethodInfo::notification::<ShowMessage>(),
+    MethodInfo::notification::<LogMessage>(),
+    MethodInfo::notification::<PublishDiagnostics>(),
+    MethodInfo::notification::<TaskStart>(),
+    MethodInfo::notification::<TaskFinish>(),
+    MethodInfo::notification::<TaskProgress>(),
+    MethodInfo::notification::<BuildTargetDidChange>(),
+];
+
+/// Find the built-in method named `method`.
+pub fn lookup(method: &str) -> Option<&'static MethodInfo> {
+    METHODS.iter().find(|info| info.method == method)
+}
+
+/// Whether a message of `method` may leave out `params`. Unknown methods are not checked, their
+/// missing params read as `null`.
+pub(crate) fn params_optional(method: &str) -> bool {
+    match lookup(method) {
+        Some(info) => !info.params_required,
+        None => true,
+    }
+}
+
+/// Whether `method` is only ever sent by the server.
+pub(crate) fn sent_by_server(method: &str) -> bool {
+    lookup(method).is_some_and(|info| info.direction == Direction::ServerToClient)
+}
+
+/// A built-in request, read into the [`Request`] variant of the same name.
+pub(crate) trait BuiltinRequest: BspRequest {
+    fn read(id: RequestId, params: &RawValue) -> serde_json::Result<Request>;
+}
+
+/// A built-in notification, read into the [`Notification`] variant of the same name.
+pub(crate) trait BuiltinNotification: BspNotification {
+    fn read(params: &RawValue) -> serde_json::Result<Notification>;
+}
+
+/// Parameters of `$/cancelRequest`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelParams {
+    /// The id of the request to cancel.
+    pub id: RequestId,
+}
+
+macro_rules! request {
+    ($(#[$attr:meta])* $name:ident, $method:literal, $direction:ident, (), $result:ty) => {
+        request!(@impl $(#[$attr])* $name, $method, $direction, (), $result, false,
+            |id, _params| Request::$name(id));
+    };
+    ($(#[$attr:meta])* $name:ident, $method:literal, $direction:ident, $params:ty, $result:ty) => {
+        request!(@impl $(#[$attr])* $name, $method, $direction, $params, $result, true,
+            |id, params| Request::$name(id, serde_json::from_str(params.get())?));
+    };
+    (@impl $(#[$attr:meta])* $name:ident, $method:literal, $direction:ident, $params:ty,
+        $result:ty, $required:literal, |$id:ident, $p:ident| $read:expr) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub enum $name {}
+
+        impl BspRequest for $name {
+            const METHOD: &'static str = $method;
+            const DIRECTION: Direction = Direction::$direction;
+            const PARAMS_REQUIRED: bool = $required;
+            type Params = $params;
+            type Result = $result;
+        }
+
+        impl BuiltinRequest for $name {
+            fn read($id: RequestId, $p: &RawValue) -> serde_json::Result<Request> {
+                Ok($read)
+            }
+        }
+    };
+}
+
+macro_rules! notification {
+    ($(#[$attr:meta])* $name:ident, $method:literal, $direction:ident, $params:ty,
+        |$p:ident| $read:expr) => {
+        notification!(@impl $(#[$attr])* $name, $method, $direction, $params, true, |$p| $read);
+    };
+    ($(#[$attr:meta])* $name:ident, $method:literal, $direction:ident, ()) => {
+        notification!(@impl $(#[$attr])* $name, $method, $direction, (), false,
+            |_params| Notification::$name);
+    };
+    ($(#[$attr:meta])* $name:ident, $method:literal, $direction:ident, $params:ty) => {
+        notification!(@impl $(#[$attr])* $name, $method, $direction, $params, true,
+            |params| Notification::$name(serde_json::from_str(params.get())?));
+    };
+    (@impl $(#[$attr:meta])* $name:ident, $method:literal, $direction:ident, $params:ty,
+        $required:literal, |$p:ident| $read:expr) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub enum $name {}
+
+        impl BspNotification for $name {
+            const METHOD: &'static str = $method;
+            const DIRECTION: Direction = Direction::$direction;
+            const PARAMS_REQUIRED: bool = $required;
+            type Params = $params;
+        }
+
+        impl BuiltinNotification for $name {
+            fn read($p: &RawValue) -> serde_json::Result<Notification> {
+                Ok($read)
+            }
+        }
+    };
+}
+
+request!(
+    /// Client->Server: Initialize Server
+    InitializeBuild,
+    "build/initialize",
+    ClientToServer,
+    types::InitializeBuild,
+    types::InitializeBuildResult
+);
+request!(
+    /// Client->Server: Shutdown server
+    Shutdown,
+    "build/shutdown",
+    ClientToServer,
+    (),
+    Option<Value>
+);
+request!(
+    /// Client->Server: Get a list of all available build targets in the workspace.
+    WorkspaceBuildTargets,
+    "workspace/buildTargets",
+    ClientToServer,
+    (),
+    types::WorkspaceBuildTargetsResult
+);
+request!(
+    /// Client->Server: Reload the build configuration.
+    WorkspaceReload,
+    "workspace/reload",
+    ClientToServer,
+    (),
+    Option<Value>
+);
+request!(
+    /// Client->Server: Get libraries of build target dependencies that are external to the
+    /// workspace.
+    BuildTargetDependencyModules,
+    "buildTarget/dependencyModules",
+    ClientToServer,
+    types::BuildTargetDependencyModules,
+    types::BuildTargetDependencyModulesResult
+);
+request!(
+    /// Client->Server: Debug build target(s)
+    DebugSessionStart,
+    "debugSession/start",
+    ClientToServer,
+    types::DebugSessionStart,
+    types::DebugSessionStartResult
+);
+request!(
+    /// Client->Server: Get text documents and directories that belong to a build target.
+    BuildTargetSources,
+    "buildTarget/sources",
+    ClientToServer,
+    types::BuildTargetSources,
+    types::BuildTargetSourcesResult
+);
+request!(
+    /// Client->Server: Get build targets containing a text document.
+    TextDocumentInverseSources,
+    "textDocument/inverseSources",
+    ClientToServer,
+    types::TextDocumentInverseSources,
+    types::BuildTargetInverseSourcesResult
+);
+request!(
+    /// Client->Server: Get sources of build target dependencies that are external to the
+    /// workspace.
+    BuildTargetDependencySources,
+    "buildTarget/dependencySources",
+    ClientToServer,
+    types::BuildTargetDependencySources,
+    types::BuildTargetDependencySourcesResult
+);
+request!(
+    /// Client->Server: Get list of resources of a given list of build targets.
+    BuildTargetResources,
+    "buildTarget/resources",
+    ClientToServer,
+    types::BuildTargetResources,
+    types::BuildTargetResourcesResult
+);
+request!(
+    /// Client->Server: Run a build target
+    BuildTargetRun,
+    "buildTarget/run",
+    ClientToServer,
+    types::BuildTargetRun,
+    types::BuildTargetRunResult
+);
+request!(
+    /// Client->Server: Run a compile target
+    BuildTargetCompile,
+    "buildTarget/compile",
+    ClientToServer,
+    types::BuildTargetCompile,
+    types::BuildTargetCompileResult
+);
+request!(
+    /// Client->Server: Run a test target
+    BuildTargetTest,
+    "buildTarget/test",
+    ClientToServer,
+    types::BuildTargetTest,
+    types::BuildTargetTestResult
+);
+request!(
+    /// Client->Server: reset any state associated with a given build target
+    BuildTargetCleanCache,
+    "buildTarget/cleanCache",
+    ClientToServer,
+    types::BuildTargetCleanCache,
+    types::BuildTargetCleanCacheResult
+);
+request!(
+    /// Client->Server: Get the environment to run the given targets in a JVM.
+    BuildTargetJvmRunEnvironment,
+    "buildTarget/jvmRunEnvironment",
+    ClientToServer,
+    types::JvmRunEnvironment,
+    types::JvmRunEnvironmentResult
+);
+request!(
+    /// Client->Server: Get the environment to test the given targets in a JVM.
+    BuildTargetJvmTestEnvironment,
+    "buildTarget/jvmTestEnvironment",
+    ClientToServer,
+    types::JvmTestEnvironment,
+    types::JvmTestEnvironmentResult
+);
+request!(
+    /// Client->Server: Get the classpath the given targets are compiled with.
+    BuildTargetJvmCompileClasspath,
+    "buildTarget/jvmCompileClasspath",
+    ClientToServer,
+    types::JvmCompileClasspath,
+    types::JvmCompileClasspathResult
+);
+request!(
+    /// Client->Server: Get the packages and dependencies of a Rust workspace.
+    BuildTargetRustWorkspace,
+    "buildTarget/rustWorkspace",
+    ClientToServer,
+    types::RustWorkspace,
+    types::RustWorkspaceResult
+);
+request!(
+    /// Client->Server: Get the state of the Cargo features of every package.
+    WorkspaceCargoFeaturesState,
+    "workspace/cargoFeaturesState",
+    ClientToServer,
+    (),
+    types::CargoFeaturesStateResult
+);
+request!(
+    /// Client->Server: Set the enabled Cargo features of a package.
+    WorkspaceSetCargoFeatures,
+    "workspace/setCargoFeatures",
+    ClientToServer,
+    types::SetCargoFeatures,
+    types::SetCargoFeaturesResult
+);
+
+notification!(
+    /// Client->Server: Ask the server to exit its process.
+    Exit,
+    "build/exit",
+    ClientToServer,
+    ()
+);
+notification!(
+    /// Client->Server: Signal that the client received the initialize result.
+    Initialized,
+    "build/initialized",
+    ClientToServer,
+    ()
+);
+notification!(
+    /// Cancel a request previously sent with the given id
+    CancelRequest,
+    "$/cancelRequest",
+    Both,
+    CancelParams,
+    |params| Notification::CancelRequest(serde_json::from_str::<CancelParams>(params.get())?.id)
+);
+notification!(
+    /// Server->Client: Ask the client to display a message.
+    ShowMessage,
+    "build/showMessage",
+    ServerToClient,
+    types::ShowMessage
+);
+notification!(
+    /// Server->Client: Ask the client to log a message.
+    LogMessage,
+    "build/logMessage",
+    ServerToClient,
+    types::LogMessage
+);
+notification!(
+    /// Server->Client: Report diagnostics for a text document.
+    PublishDiagnostics,
+    "build/publishDiagnostics",
+    ServerToClient,
+    types::PublishDiagnostics
+);
+notification!(
+    /// Server->Client: A task started.
+    TaskStart,
+    "build/taskStart",
+    ServerToClient,
+    types::TaskStart
+);
+notification!(
+    /// Server->Client: A task finished.
+    TaskFinish,
+    "build/taskFinish",
+    ServerToClient,
+    types::TaskFinish
+);
+notification!(
+    /// Server->Client: A task made progress.
+    TaskProgress,
+    "build/taskProgress",
+    ServerToClient,
+    types::TaskProgress
+);
+notification!(
+    /// Server->Client: Build targets changed.
+    BuildTargetDidChange,
+    "buildTarget/didChange",
+    ServerToClient,
+    types::BuildTargetDidChange
+);
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::fmt;
 
 use bsp_types::{
@@ -5,18 +6,21 @@ use bsp_types::{
     TaskStart,
 };
 use serde::{
-    de::{Error as DeError, MapAccess, Visitor},
+    de::{Error as DeError, IgnoredAny, MapAccess, Visitor},
     ser::SerializeStruct,
     Deserialize, Deserializer, Serialize,
 };
-use serde_json::Value;
+use serde_json::{value::RawValue, Value};
 
-use crate::Message;
+use crate::methods::{self, BspNotification, CancelParams};
+use crate::{ExtractError, Message, RequestId};
 
 #[derive(Debug, Clone)]
 pub enum Notification {
     Exit,
     Initialized,
+    /// Cancel a request previously sent with the given id
+    CancelRequest(RequestId),
     ShowMessage(ShowMessage),
     LogMessage(LogMessage),
     PublishDiagnostics(PublishDiagnostics),
@@ -24,40 +28,107 @@ pub enum Notification {
     TaskFinish(TaskFinish),
     TaskProgress(TaskProgress),
     BuildTargetDidChange(BuildTargetDidChange),
-    Custom(&'static str, Value),
+    /// Any custom notification. The method name is borrowed when the notification is built from a
+    /// literal and owned when it is read from the wire.
+    Custom(Cow<'static, str>, Value),
 }
 
 impl Notification {
-    pub fn method(&self) -> &'static str {
+    pub fn method(&self) -> &str {
         use Notification::*;
         match self {
             Exit => "build/exit",
             Initialized => "build/initialized",
+            CancelRequest(_) => "$/cancelRequest",
             ShowMessage(_) => "build/showMessage",
             LogMessage(_) => "build/logMessage",
             PublishDiagnostics(_) => "build/publishDiagnostics",
             TaskStart(_) => "build/taskStart",
             TaskFinish(_) => "build/taskFinish",
-            TaskProgress(_) => "build/taskProgressing",
+            TaskProgress(_) => "build/taskProgress",
             BuildTargetDidChange(_) => "buildTarget/didChange",
             Custom(m, _) => m,
         }
     }
 }
 
+impl Notification {
+    /// Build a notification from its decoded parts, deserializing `params` straight from the raw
+    /// text into the type expected by `method`.
+    pub(crate) fn from_parts(
+        method: String,
+        params: Option<Box<RawValue>>,
+    ) -> serde_json::Result<Notification> {
+        let params = match params {
+            Some(v) => v,
+            None if methods::params_optional(&method) => RawValue::from_string("null".into())?,
+            None => return Err(DeError::missing_field("params")),
+        };
+
+        match methods::lookup(&method) {
+            Some(info) if !info.is_request() => info.read_notification(&params),
+            _ => Ok(Notification::Custom(
+                method.into(),
+                serde_json::from_str(params.get())?,
+            )),
+        }
+    }
+}
+
+impl Notification {
+    /// Get notification params
+    pub fn params(&self) -> anyhow::Result<Value> {
+        use Notification::*;
+        let value = match self {
+            Exit | Initialized => return Ok(Value::Null),
+            CancelRequest(id) => serde_json::to_value(CancelParams { id: id.clone() }),
+            ShowMessage(params) => serde_json::to_value(params),
+            LogMessage(params) => serde_json::to_value(params),
+            PublishDiagnostics(params) => serde_json::to_value(params),
+            TaskStart(params) => serde_json::to_value(params),
+            TaskFinish(params) => serde_json::to_value(params),
+            TaskProgress(params) => serde_json::to_value(params),
+            BuildTargetDidChange(params) => serde_json::to_value(params),
+            Custom(_, params) => return Ok(params.clone()),
+        };
+
+        Ok(value?)
+    }
+
+    /// Take the params of a notification of method `N`, handing the notification back
+    /// untouched if it has another method.
+    #[allow(clippy::result_large_err)] // Hands the message back on a method mismatch.
+    pub fn extract<N: BspNotification>(self) -> Result<N::Params, ExtractError<Notification>> {
+        if self.method() != N::METHOD {
+            return Err(ExtractError::MethodMismatch(self));
+        }
+        let json_error = |error| ExtractError::JsonError {
+            method: N::METHOD.to_string(),
+            error,
+        };
+        let params = match self {
+            Notification::Custom(_, params) => params,
+            notification => notification
+                .params()
+                .map_err(|err| json_error(DeError::custom(err)))?,
+        };
+        serde_json::from_value(params).map_err(json_error)
+    }
+}
+
 impl From<(&'static str, Value)> for Notification {
     fn from(v: (&'static str, Value)) -> Self {
-        Self::Custom(v.0, v.1)
+        Self::Custom(v.0.into(), v.1)
     }
 }
 
-impl From<&str> for Notification {
-    fn from(msg: &str) -> Self {
-        match msg {
-            "build/exit" => Self::Exit,
-            "build/initialized" => Self::Initialized,
-            _ => panic!("Only exit and initialized supported."),
-        }
+/// Build a notification that takes no params, like `build/exit`, `build/initialized` or a custom
+/// notification.
+impl TryFrom<&str> for Notification {
+    type Error = serde_json::Error;
+
+    fn try_from(method: &str) -> Result<Self, Self::Error> {
+        Self::from_parts(method.to_string(), None)
     }
 }
 
@@ -69,7 +140,7 @@ impl From<Notification> for Message {
 
 impl From<(&'static str, Value)> for Message {
     fn from(v: (&'static str, Value)) -> Self {
-        Self::Notification(Notification::Custom(v.0, v.1))
+        Self::Notification(Notification::Custom(v.0.into(), v.1))
     }
 }
 
@@ -101,26 +172,45 @@ impl Serialize for Notification {
     where
         S: serde::Serializer,
     {
+        let method = self.method();
         let mut obj = s.serialize_struct("Notification", 2)?;
-        obj.serialize_field("method", self.method())?;
-
-        use Notification::*;
-        match self {
-            Exit | Initialized => {}
-            ShowMessage(m) => obj.serialize_field("params", m)?,
-            LogMessage(m) => obj.serialize_field("params", m)?,
-            PublishDiagnostics(m) => obj.serialize_field("params", m)?,
-            TaskStart(m) => obj.serialize_field("params", m)?,
-            TaskFinish(m) => obj.serialize_field("params", m)?,
-            TaskProgress(m) => obj.serialize_field("params", m)?,
-            BuildTargetDidChange(m) => obj.serialize_field("params", m)?,
-            Custom(_, m) => obj.serialize_field("params", m)?,
+        obj.serialize_field("method", method)?;
+        // Methods that take no params are written without them, see `methods::METHODS`.
+        let omit_params = match self {
+            Notification::Custom(_, params) => params.is_null() && methods::params_optional(method),
+            _ => methods::params_optional(method),
         };
-
+        if !omit_params {
+            obj.serialize_field("params", &Params(self))?;
+        }
         obj.end()
     }
 }
 
+/// The params of a notification, as written on the wire.
+struct Params<'a>(&'a Notification);
+
+impl Serialize for Params<'_> {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use Notification::*;
+        match self.0 {
+            Exit | Initialized => s.serialize_unit(),
+            CancelRequest(id) => CancelParams { id: id.clone() }.serialize(s),
+            ShowMessage(params) => params.serialize(s),
+            LogMessage(params) => params.serialize(s),
+            PublishDiagnostics(params) => params.serialize(s),
+            TaskStart(params) => params.serialize(s),
+            TaskFinish(params) => params.serialize(s),
+            TaskProgress(params) => params.serialize(s),
+            BuildTargetDidChange(params) => params.serialize(s),
+            Custom(_, params) => params.serialize(s),
+        }
+    }
+}
+
 #[cfg(test)]
 mod se {
     use super::*;
@@ -148,11 +238,33 @@ mod se {
         );
     }
 
+    #[test]
+    fn cancel_request() {
+        let value = &Notification::CancelRequest(3.into());
+        let result = serde_json::to_string(value).unwrap();
+        assert_eq!(
+            result,
+            "{\"method\":\"$/cancelRequest\",\"params\":{\"id\":3}}"
+        );
+    }
+
     #[test]
     fn custom() {
         let value = &Notification::Custom("custom".into(), Value::Null);
         let result = serde_json::to_string(value).unwrap();
-        assert_eq!(result, "{\"method\":\"custom\",\"params\":null}");
+        assert_eq!(result, "{\"method\":\"custom\"}");
+    }
+
+    #[test]
+    fn task_progress() {
+        let text = r#"{"jsonrpc":"2.0","method":"build/taskProgress","params":{"taskId":{"id":"compile"},"message":"compiling","total":10,"progress":3,"unit":"files"}}"#;
+        let notification: Notification = serde_json::from_str(text).unwrap();
+        assert!(matches!(
+            &notification,
+            Notification::TaskProgress(p) if p.progress == Some(3) && p.unit == "files"
+        ));
+        let result = serde_json::to_value(&notification).unwrap();
+        assert_eq!(result["method"], "build/taskProgress");
     }
 }
 
@@ -161,7 +273,7 @@ impl<'de> Deserialize<'de> for Notification {
     where
         D: Deserializer<'de>,
     {
-        const FIELDS: &'static [&'static str] = &["params", "method"];
+        const FIELDS: &[&str] = &["params", "method"];
         enum Field {
             Method,
             Params,
@@ -211,8 +323,8 @@ impl<'de> Deserialize<'de> for Notification {
             where
                 V: MapAccess<'de>,
             {
-                let mut params: Option<serde_json::Value> = None; // this is just lazy
-                let mut method: Option<String> = None; // required for json! to work
+                let mut params: Option<Box<RawValue>> = None;
+                let mut method: Option<String> = None;
                 while let Some(key) = map.next_key()? {
                     match key {
                         Field::Params => {
@@ -227,38 +339,14 @@ impl<'de> Deserialize<'de> for Notification {
                             }
                             method = Some(map.next_value()?);
                         }
-                        _ => (),
-                    }
-                }
-
-                let method = method.ok_or_else(|| DeError::missing_field("method"))?;
-                let params = match params {
-                    Some(v) => v,
-                    None => {
-                        if &method != "build/exit" && &method != "build/initialized" {
-                            return Err(DeError::missing_field("params"));
+                        Field::Other => {
+                            map.next_value::<IgnoredAny>()?;
                         }
-                        serde_json::Value::Null
                     }
-                };
-
-                fn de<'a, T: Deserialize<'a>, E: DeError>(p: serde_json::Value) -> Result<T, E> {
-                    T::deserialize(p).map_err(DeError::custom)
                 }
 
-                use Notification::*;
-                Ok(match method.as_str() {
-                    "build/exit" => Exit,
-                    "build/initialized" => Initialized,
-                    "build/showMessage" => ShowMessage(de(params)?),
-                    "build/logMessage" => LogMessage(de(params)?),
-                    "build/publishDiagnostics" => PublishDiagnostics(de(params)?),
-                    "build/taskStart" => TaskStart(de(params)?),
-                    "build/taskFinish" => TaskFinish(de(params)?),
-                    "build/taskProgressing" => TaskProgress(de(params)?),
-                    "buildTarget/didChange" => BuildTargetDidChange(de(params)?),
-                    _ => Custom(Box::leak(method.into_boxed_str()), params),
-                })
+                let method = method.ok_or_else(|| DeError::missing_field("method"))?;
+                Notification::from_parts(method, params).map_err(DeError::custom)
             }
         }
 
@@ -292,4 +380,145 @@ mod de {
         let result = serde_json::from_str::<Notification>(value).unwrap();
         assert!(matches!(result, Notification::TaskStart(TaskStart { .. })));
     }
+
+    #[test]
+    fn cancel_request() {
+        let value = "{\"method\":\"$/cancelRequest\",\"params\":{\"id\":\"abc\"}}";
+        let result = serde_json::from_str::<Notification>(value).unwrap();
+        assert!(
+            matches!(result, Notification::CancelRequest(id) if id == String::from("abc").into())
+        );
+    }
+
+    #[test]
+    fn try_from_method() {
+        assert!(matches!(
+            Notification::try_from("build/exit"),
+            Ok(Notification::Exit)
+        ));
+        assert!(matches!(
+            Notification::try_from("build/initialized"),
+            Ok(Notification::Initialized)
+        ));
+        assert!(Notification::try_from("build/taskStart").is_err());
+        assert!(matches!(
+            Notification::try_from("custom/method"),
+            Ok(Notification::Custom(_, Value::Null))
+        ));
+    }
+}
+
+/// Every notification the crate can send must read back as the same notification.
+#[cfg(test)]
+mod round_trip {
+    use super::*;
+    use bsp_types::{
+        BuildTargetEvent, BuildTargetIdentifier, Diagnostic, TaskId, TextDocumentIdentifier, Url,
+    };
+
+    fn round_trip(notification: Notification) -> Notification {
+        let text = serde_json::to_string(&notification).unwrap();
+        let result: Notification = serde_json::from_str(&text).unwrap();
+        assert_eq!(result.method(), notification.method());
+        assert_eq!(serde_json::to_string(&result).unwrap(), text);
+        result
+    }
+
+    fn uri() -> Url {
+        Url::parse("file:///tmp/project/src/main.rs").unwrap()
+    }
+
+    #[test]
+    fn exit() {
+        assert!(matches!(round_trip(Notification::Exit), Notification::Exit));
+    }
+
+    #[test]
+    fn initialized() {
+        let result = round_trip(Notification::Initialized);
+        assert!(matches!(result, Notification::Initialized));
+    }
+
+    #[test]
+    fn cancel_request() {
+        let result = round_trip(Notification::CancelRequest(7.into()));
+        assert!(matches!(result, Notification::CancelRequest(id) if id == 7.into()));
+    }
+
+    #[test]
+    fn show_message() {
+        let msg = ShowMessage::warn("careful", Some(TaskId::from("t")), Some("origin"));
+        let result = round_trip(msg.into());
+        assert!(matches!(result, Notification::ShowMessage(m) if m.message == "careful"));
+    }
+
+    #[test]
+    fn log_message() {
+        let msg = LogMessage::new(bsp_types::MessageType::Log, "compiling", None, None);
+        let result = round_trip(msg.into());
+        assert!(matches!(result, Notification::LogMessage(m) if m.message == "compiling"));
+    }
+
+    #[test]
+    fn publish_diagnostics() {
+        let diagnostic = Diagnostic {
+            message: "unused variable".into(),
+            ..Default::default()
+        };
+        let params = PublishDiagnostics::new(
+            TextDocumentIdentifier::new(uri()),
+            BuildTargetIdentifier::new(uri()),
+            Some("origin".into()),
+            vec![diagnostic],
+            true,
+        );
+        let result = round_trip(params.into());
+        assert!(matches!(result, Notification::PublishDiagnostics(p)
+            if p.reset() && p.diagnostics()[0].message == "unused variable"));
+    }
+
+    #[test]
+    fn task_start() {
+        let result = round_trip(TaskStart::new("task").into());
+        assert!(matches!(result, Notification::TaskStart(t) if t.task_id == TaskId::from("task")));
+    }
+
+    #[test]
+    fn task_finish() {
+        let mut params = TaskFinish::new(TaskId::from("task"));
+        params.message = "done".into();
+        let result = round_trip(params.into());
+        assert!(matches!(result, Notification::TaskFinish(t) if t.message == "done"));
+    }
+
+    #[test]
+    fn task_progress() {
+        let params = TaskProgress {
+            task_id: TaskId::from("task"),
+            total: Some(10),
+            progress: Some(3),
+            unit: "files".into(),
+            ..Default::default()
+        };
+        let result = round_trip(params.into());
+        assert!(matches!(result, Notification::TaskProgress(t) if t.progress == Some(3)));
+    }
+
+    #[test]
+    fn build_target_did_change() {
+        let params = BuildTargetDidChange {
+            changes: vec![BuildTargetEvent::new_simple(BuildTargetIdentifier::new(
+                uri(),
+            ))],
+        };
+        let result = round_trip(params.into());
+        assert!(matches!(result, Notification::BuildTargetDidChange(p) if p.changes.len() == 1));
+    }
+
+    #[test]
+    fn custom() {
+        let params = serde_json::json!({ "answer": 42 });
+        let result = round_trip(("custom/event", params.clone()).into());
+        assert!(matches!(result, Notification::Custom(m, p) if m == "custom/event" && p == params));
+    }
 }
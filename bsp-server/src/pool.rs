@@ -0,0 +1,62 @@
+use crossbeam_channel::{unbounded, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Fixed size pool of threads running request handlers.
+pub(crate) struct WorkerPool {
+    sender: Sender<Job>,
+    in_flight: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl WorkerPool {
+    /// Spawn `workers` threads (at least one) waiting for jobs.
+    pub(crate) fn new(workers: usize) -> Self {
+        let (sender, receiver) = unbounded::<Job>();
+        for i in 0..workers.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("bsp-worker-{i}"))
+                .spawn(move || receiver.into_iter().for_each(|job| job()))
+                .expect("failed to spawn worker thread");
+        }
+        Self {
+            sender,
+            in_flight: Default::default(),
+        }
+    }
+
+    /// Queue a job to run on the next free worker.
+    pub(crate) fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        *self.in_flight.0.lock().unwrap() += 1;
+        let guard = InFlight(self.in_flight.clone());
+        let job = move || {
+            let _guard = guard;
+            job()
+        };
+        self.sender.send(Box::new(job)).unwrap();
+    }
+
+    /// Block until every queued job has finished.
+    pub(crate) fn wait_idle(&self) {
+        let (count, idle) = &*self.in_flight;
+        let _count = idle
+            .wait_while(count.lock().unwrap(), |count| *count > 0)
+            .unwrap();
+    }
+}
+
+/// Decrements the in-flight counter once a job is done, even if it panicked.
+struct InFlight(Arc<(Mutex<usize>, Condvar)>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let (count, idle) = &*self.0;
+        let mut count = count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            idle.notify_all();
+        }
+    }
+}
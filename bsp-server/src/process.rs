@@ -0,0 +1,75 @@
+use crate::{Connection, IoThreads, Transporter};
+use std::io;
+use std::process::{Child, Command, ExitStatus, Stdio};
+
+/// Handle to a build server running as a child process.
+///
+/// Returned by [`Connection::spawn`] to monitor, kill or restart the server. Dropping the handle
+/// does not kill the process, it is expected to exit once it receives `build/exit`.
+#[derive(Debug)]
+pub struct ServerProcess {
+    command: Command,
+    child: Child,
+}
+
+impl ServerProcess {
+    /// Spawn `command` with piped stdio, returning a connection over its stdin/stdout.
+    pub(crate) fn spawn(mut command: Command) -> io::Result<(Connection, IoThreads, Self)> {
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let (conn, io_threads, child) = Self::start(&mut command)?;
+        Ok((conn, io_threads, Self { command, child }))
+    }
+
+    fn start(command: &mut Command) -> io::Result<(Connection, IoThreads, Child)> {
+        let mut child = command.spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take();
+        let (conn, io_threads) =
+            Connection::from_transporter(Transporter::child(stdin, stdout, stderr));
+        Ok((conn, io_threads, child))
+    }
+
+    /// OS-assigned process identifier of the server.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// The command the server was spawned with.
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+
+    /// Returns the exit status if the server exited, without blocking.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.child.try_wait()
+    }
+
+    /// Block until the server exits.
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.child.wait()
+    }
+
+    /// Kill the server and wait for it to exit.
+    pub fn kill(&mut self) -> io::Result<ExitStatus> {
+        if let Some(status) = self.child.try_wait()? {
+            return Ok(status);
+        }
+        self.child.kill()?;
+        self.child.wait()
+    }
+
+    /// Kill the server if it is still running and spawn it again with the same command,
+    /// returning a connection to the new process.
+    ///
+    /// The new server has to be initialized again.
+    pub fn restart(&mut self) -> io::Result<(Connection, IoThreads)> {
+        self.kill()?;
+        let (conn, io_threads, child) = Self::start(&mut self.command)?;
+        self.child = child;
+        Ok((conn, io_threads))
+    }
+}
@@ -1,6 +1,11 @@
-use super::{ErrorCode, Request, RequestId, Response, ResponseError};
+use super::{ErrorCode, ExtractError, Request, RequestId, Response, ResponseError};
+use crate::methods::BspRequest;
+use bsp_types::CancellationToken;
+use crossbeam_channel::Receiver;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
 /// Manages the set of pending requests, both incomming and outgoing.
 #[derive(Debug)]
@@ -12,13 +17,8 @@ pub struct ReqQueue<I, O> {
 impl<I, O> Default for ReqQueue<I, O> {
     fn default() -> ReqQueue<I, O> {
         ReqQueue {
-            incoming: Incoming {
-                pending: HashMap::default(),
-            },
-            outgoing: Outgoing {
-                next_id: 0,
-                pending: HashMap::default(),
-            },
+            incoming: Incoming::default(),
+            outgoing: Outgoing::default(),
         }
     }
 }
@@ -28,10 +28,39 @@ pub struct Incoming<I> {
     pending: HashMap<RequestId, I>,
 }
 
+impl<I> Default for Incoming<I> {
+    fn default() -> Incoming<I> {
+        Incoming {
+            pending: HashMap::default(),
+        }
+    }
+}
+
+/// Requests sent to the other side that are waiting for a response.
+///
+/// Ids are allocated monotonically, and every request carries a piece of pending state `O`
+/// that is handed back when the matching [`Response`] arrives.
 #[derive(Debug)]
 pub struct Outgoing<O> {
     next_id: i32,
-    pending: HashMap<RequestId, O>,
+    timeout: Option<Duration>,
+    pending: HashMap<RequestId, Pending<O>>,
+}
+
+#[derive(Debug)]
+struct Pending<O> {
+    data: O,
+    deadline: Option<Instant>,
+}
+
+impl<O> Default for Outgoing<O> {
+    fn default() -> Outgoing<O> {
+        Outgoing {
+            next_id: 0,
+            timeout: None,
+            pending: HashMap::default(),
+        }
+    }
 }
 
 impl<I> Incoming<I> {
@@ -46,7 +75,7 @@ impl<I> Incoming<I> {
             data: None,
         };
         Some(Response {
-            id,
+            id: Some(id),
             result: None,
             error: Some(error),
         })
@@ -54,16 +83,167 @@ impl<I> Incoming<I> {
     pub fn complete(&mut self, id: RequestId) -> Option<I> {
         self.pending.remove(&id)
     }
+    pub fn get(&self, id: &RequestId) -> Option<&I> {
+        self.pending.get(id)
+    }
+    /// Returns `true` if no request is pending.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+impl Incoming<CancellationToken> {
+    /// Cancel the token of a pending request, leaving it pending until its handler returns.
+    /// Returns `false` if no request with `id` is pending.
+    pub fn cancel_token(&self, id: &RequestId) -> bool {
+        self.pending
+            .get(id)
+            .map(CancellationToken::cancel)
+            .is_some()
+    }
 }
 
 impl<O> Outgoing<O> {
-    pub fn register<P: Serialize>(&mut self, method: String, params: P, data: O) -> Request {
+    /// Set how long requests registered from now on may wait for their response.
+    /// `None` (the default) waits forever.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Register a custom request with the given method and params, failing if the params can't
+    /// be serialized.
+    pub fn register<P: Serialize>(
+        &mut self,
+        method: &'static str,
+        params: P,
+        data: O,
+    ) -> serde_json::Result<Request> {
+        let params = serde_json::to_value(params)?;
+        Ok(self.register_with(|id| Request::Custom(id, method.into(), params), data))
+    }
+
+    /// Register a request built by `request` from the next allocated id.
+    pub fn register_with(
+        &mut self,
+        request: impl FnOnce(RequestId) -> Request,
+        data: O,
+    ) -> Request {
         let id = RequestId::from(self.next_id);
-        self.pending.insert(id.clone(), data);
         self.next_id += 1;
-        Request::new(id, method, params)
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        self.pending.insert(id.clone(), Pending { data, deadline });
+        request(id)
     }
+
+    /// Take the pending state of the request answered by `id`.
+    /// Returns `None` for unknown or already expired requests.
     pub fn complete(&mut self, id: RequestId) -> Option<O> {
-        self.pending.remove(&id)
+        self.pending.remove(&id).map(|pending| pending.data)
+    }
+
+    /// Returns `true` if the request with `id` is still waiting for a response.
+    pub fn is_pending(&self, id: &RequestId) -> bool {
+        self.pending.contains_key(id)
+    }
+
+    /// The earliest deadline among pending requests, suitable for `Receiver::recv_deadline`.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.values().filter_map(|p| p.deadline).min()
+    }
+
+    /// Remove and return the requests whose deadline is at or before `now`, oldest first.
+    pub fn expire(&mut self, now: Instant) -> Vec<(RequestId, O)> {
+        let mut expired: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| p.deadline.is_some_and(|deadline| deadline <= now))
+            .map(|(id, _)| id.clone())
+            .collect();
+        expired.sort();
+        expired
+            .into_iter()
+            .filter_map(|id| Some((id.clone(), self.complete(id)?)))
+            .collect()
+    }
+
+    /// Remove and return every pending request.
+    pub fn clear(&mut self) -> Vec<(RequestId, O)> {
+        self.pending
+            .drain()
+            .map(|(id, pending)| (id, pending.data))
+            .collect()
+    }
+}
+
+/// A request sent with [`crate::Connection::send_request`], waiting for its response.
+#[derive(Debug)]
+pub struct PendingResponse<R> {
+    id: RequestId,
+    receiver: Receiver<Response>,
+    request: PhantomData<fn() -> R>,
+}
+
+impl<R: BspRequest> PendingResponse<R> {
+    pub(crate) fn new(id: RequestId, receiver: Receiver<Response>) -> Self {
+        Self {
+            id,
+            receiver,
+            request: PhantomData,
+        }
+    }
+
+    /// Get the id of the request.
+    pub fn id(&self) -> &RequestId {
+        &self.id
+    }
+
+    /// Block until the response arrives and read its result.
+    ///
+    /// Requests left unanswered past their timeout fail with `RequestCanceled`, and requests
+    /// still pending when the connection stops being served with `InternalError`. Must not be
+    /// called from a handler run by [`Connection::serve`](crate::Connection::serve), which
+    /// only reads the response once the handler returns.
+    pub fn wait(self) -> Result<R::Result, ExtractError<Response>> {
+        let resp = self.receiver.recv().unwrap_or_else(|_| {
+            let msg = "connection closed before the request was answered".to_string();
+            Response::internal_error(self.id, msg)
+        });
+        resp.extract::<R>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outgoing_ids_are_monotonic() {
+        let mut outgoing = Outgoing::default();
+        let first = outgoing.register("custom/first", (), "first").unwrap();
+        let second = outgoing.register_with(Request::WorkspaceBuildTargets, "second");
+        assert_eq!(first.id(), &0.into());
+        assert_eq!(second.id(), &1.into());
+
+        assert_eq!(outgoing.complete(1.into()), Some("second"));
+        assert_eq!(outgoing.complete(1.into()), None);
+        let third = outgoing.register("custom/third", (), "third").unwrap();
+        assert_eq!(third.id(), &2.into());
+    }
+
+    #[test]
+    fn outgoing_expire() {
+        let mut outgoing = Outgoing::default();
+        outgoing.register("custom/forever", (), "forever").unwrap();
+        outgoing.set_timeout(Some(Duration::from_secs(5)));
+        outgoing.register("custom/timeout", (), "timeout").unwrap();
+
+        let deadline = outgoing.next_deadline().unwrap();
+        assert!(outgoing
+            .expire(deadline - Duration::from_secs(1))
+            .is_empty());
+        assert_eq!(outgoing.expire(deadline), vec![(1.into(), "timeout")]);
+        assert!(!outgoing.is_pending(&1.into()));
+        assert!(outgoing.is_pending(&0.into()));
+        assert_eq!(outgoing.next_deadline(), None);
     }
 }
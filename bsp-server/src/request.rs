@@ -1,18 +1,20 @@
 mod id;
 
-use crate::Message;
+use crate::methods::{self, BspRequest};
+use crate::{ExtractError, Message};
+use std::borrow::Cow;
 use std::fmt;
 
 use bsp_types::*;
 pub use id::*;
 
 use serde::{
-    de::{Error as DeError, MapAccess, Visitor},
+    de::{Error as DeError, IgnoredAny, MapAccess, Visitor},
     ser::SerializeStruct,
     Deserialize, Deserializer, Serialize,
 };
 
-use serde_json::Value;
+use serde_json::{value::RawValue, Value};
 
 #[derive(Clone)]
 pub enum Request {
@@ -47,13 +49,26 @@ pub enum Request {
     BuildTargetTest(RequestId, BuildTargetTest),
     /// Client->Server: reset any state associated with a given build target
     BuildTargetCleanCache(RequestId, BuildTargetCleanCache),
-    /// Any custom message not yet supported in the crate or custom
-    Custom(RequestId, &'static str, Value),
+    /// Client->Server: Get the environment to run the given targets in a JVM.
+    BuildTargetJvmRunEnvironment(RequestId, JvmRunEnvironment),
+    /// Client->Server: Get the environment to test the given targets in a JVM.
+    BuildTargetJvmTestEnvironment(RequestId, JvmTestEnvironment),
+    /// Client->Server: Get the classpath the given targets are compiled with.
+    BuildTargetJvmCompileClasspath(RequestId, JvmCompileClasspath),
+    /// Client->Server: Get the packages and dependencies of a Rust workspace.
+    BuildTargetRustWorkspace(RequestId, RustWorkspace),
+    /// Client->Server: Get the state of the Cargo features of every package.
+    WorkspaceCargoFeaturesState(RequestId),
+    /// Client->Server: Set the enabled Cargo features of a package.
+    WorkspaceSetCargoFeatures(RequestId, SetCargoFeatures),
+    /// Any custom message not yet supported in the crate or custom. The method name is borrowed
+    /// when the request is built from a literal and owned when it is read from the wire.
+    Custom(RequestId, Cow<'static, str>, Value),
 }
 
 impl Request {
     /// Get request method
-    pub fn method(&self) -> &'static str {
+    pub fn method(&self) -> &str {
         use Request::*;
         match self {
             InitializeBuild(_, _) => "build/initialize",
@@ -70,6 +85,12 @@ impl Request {
             BuildTargetCompile(_, _) => "buildTarget/compile",
             BuildTargetTest(_, _) => "buildTarget/test",
             BuildTargetCleanCache(_, _) => "buildTarget/cleanCache",
+            BuildTargetJvmRunEnvironment(_, _) => "buildTarget/jvmRunEnvironment",
+            BuildTargetJvmTestEnvironment(_, _) => "buildTarget/jvmTestEnvironment",
+            BuildTargetJvmCompileClasspath(_, _) => "buildTarget/jvmCompileClasspath",
+            BuildTargetRustWorkspace(_, _) => "buildTarget/rustWorkspace",
+            WorkspaceCargoFeaturesState(_) => "workspace/cargoFeaturesState",
+            WorkspaceSetCargoFeatures(_, _) => "workspace/setCargoFeatures",
             Custom(_, m, _) => m,
         }
     }
@@ -92,6 +113,12 @@ impl Request {
             | BuildTargetCompile(id, _)
             | BuildTargetTest(id, _)
             | BuildTargetCleanCache(id, _)
+            | BuildTargetJvmRunEnvironment(id, _)
+            | BuildTargetJvmTestEnvironment(id, _)
+            | BuildTargetJvmCompileClasspath(id, _)
+            | BuildTargetRustWorkspace(id, _)
+            | WorkspaceCargoFeaturesState(id)
+            | WorkspaceSetCargoFeatures(id, _)
             | Custom(id, _, _) => id,
         }
     }
@@ -100,7 +127,10 @@ impl Request {
     pub fn params(&self) -> anyhow::Result<Value> {
         use Request::*;
         let value = match self {
-            Shutdown(_) | WorkspaceBuildTargets(_) | WorkspaceReload(_) => return Ok(Value::Null),
+            Shutdown(_)
+            | WorkspaceBuildTargets(_)
+            | WorkspaceReload(_)
+            | WorkspaceCargoFeaturesState(_) => return Ok(Value::Null),
             InitializeBuild(_, ref params) => serde_json::to_value(params),
             BuildTargetDependencyModules(_, ref params) => serde_json::to_value(params),
             DebugSessionStart(_, ref params) => serde_json::to_value(params),
@@ -112,13 +142,63 @@ impl Request {
             BuildTargetCompile(_, ref params) => serde_json::to_value(params),
             BuildTargetTest(_, ref params) => serde_json::to_value(params),
             BuildTargetCleanCache(_, ref params) => serde_json::to_value(params),
-            Custom(_, ref params, _) => serde_json::to_value(params),
+            BuildTargetJvmRunEnvironment(_, ref params) => serde_json::to_value(params),
+            BuildTargetJvmTestEnvironment(_, ref params) => serde_json::to_value(params),
+            BuildTargetJvmCompileClasspath(_, ref params) => serde_json::to_value(params),
+            BuildTargetRustWorkspace(_, ref params) => serde_json::to_value(params),
+            WorkspaceSetCargoFeatures(_, ref params) => serde_json::to_value(params),
+            Custom(_, _, ref params) => return Ok(params.clone()),
         };
 
         Ok(value?)
     }
 }
 
+impl Request {
+    /// Build a request from its decoded parts, deserializing `params` straight from the raw
+    /// text into the type expected by `method`.
+    pub(crate) fn from_parts(
+        id: RequestId,
+        method: String,
+        params: Option<Box<RawValue>>,
+    ) -> serde_json::Result<Request> {
+        let params = match params {
+            Some(v) => v,
+            None if methods::params_optional(&method) => RawValue::from_string("null".into())?,
+            None => return Err(DeError::missing_field("params")),
+        };
+
+        match methods::lookup(&method) {
+            Some(info) if info.is_request() => info.read_request(id, &params),
+            _ => Ok(Request::Custom(
+                id,
+                method.into(),
+                serde_json::from_str(params.get())?,
+            )),
+        }
+    }
+}
+
+impl Request {
+    /// Take the id and params of a request of method `R`, handing the request back untouched
+    /// if it has another method.
+    #[allow(clippy::result_large_err)] // Hands the message back on a method mismatch.
+    pub fn extract<R: BspRequest>(self) -> Result<(RequestId, R::Params), ExtractError<Request>> {
+        if self.method() != R::METHOD {
+            return Err(ExtractError::MethodMismatch(self));
+        }
+        let json_error = |error| ExtractError::JsonError {
+            method: R::METHOD.to_string(),
+            error,
+        };
+        let params = self
+            .params()
+            .map_err(|err| json_error(DeError::custom(err)))?;
+        let params = serde_json::from_value(params).map_err(json_error)?;
+        Ok((self.id().clone(), params))
+    }
+}
+
 impl From<Request> for Message {
     fn from(request: Request) -> Message {
         Message::Request(request)
@@ -127,27 +207,30 @@ impl From<Request> for Message {
 
 impl From<(RequestId, &'static str, Value)> for Request {
     fn from(v: (RequestId, &'static str, Value)) -> Self {
-        Self::Custom(v.0.into(), v.1, v.2)
+        Self::Custom(v.0, v.1.into(), v.2)
     }
 }
 
 impl From<(RequestId, &'static str, Value)> for Message {
     fn from(v: (RequestId, &'static str, Value)) -> Self {
-        Self::Request((v.0.into(), v.1, v.2).into())
+        Self::Request((v.0, v.1, v.2).into())
     }
 }
 
 macro_rules! convertible {
     ($p:ident) => {
+        convertible!($p, $p);
+    };
+    ($variant:ident, $p:ident) => {
         impl From<(RequestId, $p)> for Request {
             fn from(v: (RequestId, $p)) -> Self {
-                Self::$p(v.0, v.1)
+                Self::$variant(v.0, v.1)
             }
         }
 
         impl From<(RequestId, $p)> for Message {
             fn from(v: (RequestId, $p)) -> Self {
-                Self::Request(crate::Request::$p(v.0, v.1))
+                Self::Request(crate::Request::$variant(v.0, v.1))
             }
         }
     };
@@ -164,6 +247,11 @@ convertible!(BuildTargetTest);
 convertible!(DebugSessionStart);
 convertible!(InitializeBuild);
 convertible!(TextDocumentInverseSources);
+convertible!(BuildTargetJvmRunEnvironment, JvmRunEnvironment);
+convertible!(BuildTargetJvmTestEnvironment, JvmTestEnvironment);
+convertible!(BuildTargetJvmCompileClasspath, JvmCompileClasspath);
+convertible!(BuildTargetRustWorkspace, RustWorkspace);
+convertible!(WorkspaceSetCargoFeatures, SetCargoFeatures);
 
 impl fmt::Debug for Request {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -191,6 +279,14 @@ impl fmt::Debug for Request {
             Request::BuildTargetCompile(id, value) => format(f, id, value),
             Request::BuildTargetTest(id, value) => format(f, id, value),
             Request::BuildTargetCleanCache(id, value) => format(f, id, value),
+            Request::BuildTargetJvmRunEnvironment(id, value) => format(f, id, value),
+            Request::BuildTargetJvmTestEnvironment(id, value) => format(f, id, value),
+            Request::BuildTargetJvmCompileClasspath(id, value) => format(f, id, value),
+            Request::BuildTargetRustWorkspace(id, value) => format(f, id, value),
+            Request::WorkspaceCargoFeaturesState(id) => {
+                format(f, id, "WorkspaceCargoFeaturesState")
+            }
+            Request::WorkspaceSetCargoFeatures(id, value) => format(f, id, value),
             Request::Custom(id, method, value) => {
                 fmt::Display::fmt(&id, f)?;
                 f.write_str(", ")?;
@@ -208,82 +304,56 @@ impl Serialize for Request {
         S: serde::Serializer,
     {
         let method = self.method();
-        let mut obj = s.serialize_struct("Request", 2)?;
-
-        use Request::*;
-        match self {
-            InitializeBuild(id, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                obj.serialize_field("params", value)?;
-            }
-            // TODO: Should it set value to None?
-            Shutdown(id) | WorkspaceBuildTargets(id) | WorkspaceReload(id) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-            }
-            BuildTargetDependencyModules(id, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                obj.serialize_field("params", value)?;
-            }
-            DebugSessionStart(id, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                obj.serialize_field("params", value)?;
-            }
-            BuildTargetSources(id, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                obj.serialize_field("params", value)?;
-            }
-            TextDocumentInverseSources(id, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                obj.serialize_field("params", value)?;
-            }
-            BuildTargetDependencySources(id, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                obj.serialize_field("params", value)?;
-            }
-            BuildTargetResources(id, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                obj.serialize_field("params", value)?;
-            }
-            BuildTargetRun(id, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                obj.serialize_field("params", value)?;
-            }
-            BuildTargetCompile(id, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                obj.serialize_field("params", value)?;
-            }
-            BuildTargetTest(id, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                obj.serialize_field("params", value)?;
-            }
-            BuildTargetCleanCache(id, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                obj.serialize_field("params", value)?;
-            }
-            Custom(id, _, value) => {
-                obj.serialize_field("id", id)?;
-                obj.serialize_field("method", method)?;
-                if !value.is_null() {
-                    obj.serialize_field("params", value)?;
-                }
-            }
+        let mut obj = s.serialize_struct("Request", 3)?;
+        obj.serialize_field("id", self.id())?;
+        obj.serialize_field("method", method)?;
+        // Methods that take no params are written without them, see `methods::METHODS`.
+        let omit_params = match self {
+            Request::Custom(_, _, params) => params.is_null() && methods::params_optional(method),
+            _ => methods::params_optional(method),
         };
+        if !omit_params {
+            obj.serialize_field("params", &Params(self))?;
+        }
         obj.end()
     }
 }
 
+/// The params of a request, as written on the wire.
+struct Params<'a>(&'a Request);
+
+impl Serialize for Params<'_> {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use Request::*;
+        match self.0 {
+            Shutdown(_)
+            | WorkspaceBuildTargets(_)
+            | WorkspaceReload(_)
+            | WorkspaceCargoFeaturesState(_) => s.serialize_unit(),
+            InitializeBuild(_, params) => params.serialize(s),
+            BuildTargetDependencyModules(_, params) => params.serialize(s),
+            DebugSessionStart(_, params) => params.serialize(s),
+            BuildTargetSources(_, params) => params.serialize(s),
+            TextDocumentInverseSources(_, params) => params.serialize(s),
+            BuildTargetDependencySources(_, params) => params.serialize(s),
+            BuildTargetResources(_, params) => params.serialize(s),
+            BuildTargetRun(_, params) => params.serialize(s),
+            BuildTargetCompile(_, params) => params.serialize(s),
+            BuildTargetTest(_, params) => params.serialize(s),
+            BuildTargetCleanCache(_, params) => params.serialize(s),
+            BuildTargetJvmRunEnvironment(_, params) => params.serialize(s),
+            BuildTargetJvmTestEnvironment(_, params) => params.serialize(s),
+            BuildTargetJvmCompileClasspath(_, params) => params.serialize(s),
+            BuildTargetRustWorkspace(_, params) => params.serialize(s),
+            WorkspaceSetCargoFeatures(_, params) => params.serialize(s),
+            Custom(_, _, params) => params.serialize(s),
+        }
+    }
+}
+
 #[cfg(test)]
 mod se {
     use serde_json::to_string;
@@ -328,7 +398,7 @@ mod se {
 
     #[test]
     fn custom() {
-        let value = &Request::Custom(3.into(), "some/method", Value::Null);
+        let value = &Request::Custom(3.into(), "some/method".into(), Value::Null);
         let result = to_string(value).unwrap();
         assert_eq!(result, "{\"id\":3,\"method\":\"some/method\"}");
     }
@@ -339,7 +409,7 @@ impl<'de> Deserialize<'de> for Request {
     where
         D: Deserializer<'de>,
     {
-        const FIELDS: &'static [&'static str] = &["id", "method", "params"];
+        const FIELDS: &[&str] = &["id", "method", "params"];
         enum Field {
             ID,
             Method,
@@ -391,9 +461,9 @@ impl<'de> Deserialize<'de> for Request {
             where
                 V: MapAccess<'de>,
             {
-                let mut id: Option<Value> = None; //  not sure maybe string maybe i32
-                let mut method: Option<String> = None; // required for json! to work
-                let mut params: Option<Value> = None; // this is just lazy
+                let mut id: Option<RequestId> = None;
+                let mut method: Option<String> = None;
+                let mut params: Option<Box<RawValue>> = None;
 
                 while let Some(key) = map.next_key()? {
                     match key {
@@ -415,52 +485,15 @@ impl<'de> Deserialize<'de> for Request {
                             }
                             method = Some(map.next_value()?);
                         }
-                        _ => (),
+                        Field::Other => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
                     }
                 }
 
-                fn de<'a, T: Deserialize<'a>, E: DeError>(p: serde_json::Value) -> Result<T, E> {
-                    T::deserialize(p).map_err(DeError::custom)
-                }
-
+                let id = id.ok_or_else(|| DeError::missing_field("id"))?;
                 let method = method.ok_or_else(|| DeError::missing_field("method"))?;
-                let id = de::<RequestId, _>(id.ok_or_else(|| DeError::missing_field("id"))?)?;
-                let params = match params {
-                    Some(v) => v,
-                    None => {
-                        if &method != "build/shutdown"
-                            || &method != "workspace/buildTargets"
-                            || &method != "workspace/reload"
-                        {
-                            return Err(DeError::missing_field("params"));
-                        }
-                        serde_json::Value::Null
-                    }
-                };
-
-                Ok(match method.as_str() {
-                    "build/initialize" => Request::InitializeBuild(id, de(params)?),
-                    "build/shutdown" => Request::Shutdown(id),
-                    "workspace/buildTargets" => Request::WorkspaceBuildTargets(id),
-                    "workspace/reload" => Request::WorkspaceReload(id),
-                    "buildTarget/dependencyModules" => {
-                        Request::BuildTargetDependencyModules(id, de(params)?)
-                    }
-                    "debugSession/start" => Request::DebugSessionStart(id, de(params)?),
-                    "buildTarget/sources" => Request::BuildTargetSources(id, de(params)?),
-                    "textDocument/inverseSources" => {
-                        Request::TextDocumentInverseSources(id, de(params)?)
-                    }
-                    "buildTarget/dependencySources" => {
-                        Request::BuildTargetDependencySources(id, de(params)?)
-                    }
-                    "buildTarget/resources" => Request::BuildTargetResources(id, de(params)?),
-                    "buildTarget/run" => Request::BuildTargetRun(id, de(params)?),
-                    "buildTarget/compile" => Request::BuildTargetCompile(id, de(params)?),
-                    "buildTarget/test" => Request::BuildTargetTest(id, de(params)?),
-                    "buildTarget/cleanCache" => Request::BuildTargetCleanCache(id, de(params)?),
-                    _ => Request::Custom(id, Box::leak(method.into_boxed_str()), params),
-                })
+                Request::from_parts(id, method, params).map_err(DeError::custom)
             }
         }
 
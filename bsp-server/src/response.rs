@@ -1,12 +1,13 @@
-use crate::{ErrorCode, Message, RequestId};
-use serde::{Deserialize, Serialize};
+use crate::methods::BspRequest;
+use crate::{ErrorCode, ExtractError, Message, RequestId};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Response {
-    // JSON RPC allows this to be null if it was impossible
-    // to decode the request's id. Ignore this special case
-    // and just die horribly.
-    pub id: RequestId,
+    /// The id of the answered request, `None` (serialized as `null`) if it was impossible to
+    /// decode the request's id, as for a `ParseError`.
+    #[serde(deserialize_with = "nullable_id")]
+    pub id: Option<RequestId>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -24,67 +25,85 @@ pub struct ResponseError {
 impl Response {
     pub fn ok<R: Serialize>(id: RequestId, result: R) -> Response {
         Response {
-            id,
+            id: Some(id),
             result: Some(serde_json::to_value(result).unwrap()),
             error: None,
         }
     }
 
-    pub fn err(id: RequestId, code: i32, message: String) -> Response {
+    pub fn err(id: impl Into<Option<RequestId>>, code: i32, message: String) -> Response {
         let error = ResponseError {
             code,
             message,
             data: None,
         };
         Response {
-            id,
+            id: id.into(),
             result: None,
             error: Some(error),
         }
     }
 
-    pub fn parse_error(id: RequestId, message: String) -> Response {
-        Self::err(id, ErrorCode::ParseError as i32 as i32, message)
+    pub fn parse_error(id: impl Into<Option<RequestId>>, message: String) -> Response {
+        Self::err(id, ErrorCode::ParseError as i32, message)
     }
 
     pub fn server_not_initialized(id: RequestId, message: String) -> Response {
         Self::err(id, ErrorCode::ServerNotInitialized as i32, message)
     }
 
-    pub fn invalid_request(id: RequestId, message: String) -> Response {
-        Self::err(id, ErrorCode::InvalidRequest as i32 as i32, message)
+    pub fn invalid_request(id: impl Into<Option<RequestId>>, message: String) -> Response {
+        Self::err(id, ErrorCode::InvalidRequest as i32, message)
     }
 
     pub fn method_not_found(id: RequestId, message: String) -> Response {
-        Self::err(id, ErrorCode::MethodNotFound as i32 as i32, message)
+        Self::err(id, ErrorCode::MethodNotFound as i32, message)
     }
 
     pub fn invalid_params(id: RequestId, message: String) -> Response {
-        Self::err(id, ErrorCode::InvalidParams as i32 as i32, message)
+        Self::err(id, ErrorCode::InvalidParams as i32, message)
     }
 
     pub fn internal_error(id: RequestId, message: String) -> Response {
-        Self::err(id, ErrorCode::InternalError as i32 as i32, message)
+        Self::err(id, ErrorCode::InternalError as i32, message)
     }
 
     pub fn server_error_start(id: RequestId, message: String) -> Response {
-        Self::err(id, ErrorCode::ServerErrorStart as i32 as i32, message)
+        Self::err(id, ErrorCode::ServerErrorStart as i32, message)
     }
 
     pub fn server_error_end(id: RequestId, message: String) -> Response {
-        Self::err(id, ErrorCode::ServerErrorEnd as i32 as i32, message)
+        Self::err(id, ErrorCode::ServerErrorEnd as i32, message)
     }
 
     pub fn request_canceled(id: RequestId, message: String) -> Response {
-        Self::err(id, ErrorCode::RequestCanceled as i32 as i32, message)
+        Self::err(id, ErrorCode::RequestCanceled as i32, message)
     }
 
     pub fn content_modified(id: RequestId, message: String) -> Response {
-        Self::err(id, ErrorCode::ContentModified as i32 as i32, message)
+        Self::err(id, ErrorCode::ContentModified as i32, message)
     }
 
     pub fn server_canceled(id: RequestId, message: String) -> Response {
-        Self::err(id, ErrorCode::ServerCancelled as i32 as i32, message)
+        Self::err(id, ErrorCode::ServerCancelled as i32, message)
+    }
+}
+
+impl Response {
+    /// Take the result of a response to a request of method `R`.
+    ///
+    /// A response carrying an error instead of a result fails with
+    /// [`ExtractError::ResponseError`]. A missing result reads as `null`.
+    pub fn extract<R: BspRequest>(self) -> Result<R::Result, ExtractError<Response>> {
+        if let Some(err) = self.error {
+            return Err(ExtractError::ResponseError(err));
+        }
+        serde_json::from_value(self.result.unwrap_or_default()).map_err(|error| {
+            ExtractError::JsonError {
+                method: R::METHOD.to_string(),
+                error,
+            }
+        })
     }
 }
 
@@ -123,3 +142,15 @@ convertible!(bsp_types::BuildTargetDependencyModulesResult);
 convertible!(bsp_types::BuildTargetCleanCacheResult);
 convertible!(bsp_types::BuildTargetInverseSourcesResult);
 convertible!(bsp_types::BuildTargetDependencySourcesResult);
+convertible!(bsp_types::JvmRunEnvironmentResult);
+convertible!(bsp_types::JvmTestEnvironmentResult);
+convertible!(bsp_types::JvmCompileClasspathResult);
+convertible!(bsp_types::RustWorkspaceResult);
+convertible!(bsp_types::CargoFeaturesStateResult);
+convertible!(bsp_types::SetCargoFeaturesResult);
+
+/// Deserialize a required but nullable id, so that messages without an id (notifications) are
+/// not mistaken for responses.
+fn nullable_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<RequestId>, D::Error> {
+    Option::deserialize(deserializer)
+}
@@ -1,4 +1,4 @@
-use crate::{Message, Request};
+use crate::{Message, Notification, Request};
 
 #[test]
 fn shutdown_with_explicit_null() {
@@ -9,15 +9,164 @@ fn shutdown_with_explicit_null() {
 }
 
 #[test]
-#[ignore = "requires implementation of response or figuring out a way to ignore params in custom serde::de"]
 fn shutdown_with_no_params() {
     let text = "{\"jsonrpc\": \"2.0\",\"id\": 3,\"method\": \"build/shutdown\"}";
     let msg: Message = serde_json::from_str(text).unwrap();
-    dbg!(&msg);
 
     assert!(matches!(msg, Message::Request(Request::Shutdown(id)) if id == 3.into()));
 }
 
+#[test]
+fn deserialize_params_in_place() {
+    let text = r#"{"jsonrpc":"2.0","id":"a","method":"buildTarget/sources","params":{"targets":[{"uri":"file:///a"}]},"extra":[1,{"b":null}]}"#;
+    let msg: Message = serde_json::from_str(text).unwrap();
+    assert!(
+        matches!(msg, Message::Request(Request::BuildTargetSources(id, params))
+        if id == "a".to_string().into() && params.targets().len() == 1)
+    );
+
+    let text = r#"{"jsonrpc":"2.0","method":"custom/ping","params":{"n":1}}"#;
+    let msg: Message = serde_json::from_str(text).unwrap();
+    assert!(
+        matches!(msg, Message::Notification(Notification::Custom(method, params))
+        if method == "custom/ping" && params == serde_json::json!({ "n": 1 }))
+    );
+
+    let text = r#"{"jsonrpc":"2.0","id":"a","method":"build/shutdown","params":{}}"#;
+    let reparsed: Request = serde_json::from_value(serde_json::from_str(text).unwrap()).unwrap();
+    assert!(matches!(reparsed, Request::Shutdown(_)));
+}
+
+#[test]
+fn compile_params_follow_spec() {
+    let text = r#"{"jsonrpc":"2.0","id":1,"method":"buildTarget/compile","params":{"targets":[{"uri":"file:///a"},{"uri":"file:///b"}],"arguments":["--release"]}}"#;
+    let msg: Message = serde_json::from_str(text).unwrap();
+    assert!(
+        matches!(msg, Message::Request(Request::BuildTargetCompile(_, params))
+        if params.targets().len() == 2 && params.arguments() == Some(&["--release".to_string()][..]))
+    );
+
+    let result =
+        crate::types::BuildTargetCompileResult::new_simple(crate::types::StatusCode::Cancelled);
+    assert_eq!(
+        serde_json::to_value(result).unwrap(),
+        serde_json::json!({ "statusCode": 3 })
+    );
+}
+
+#[test]
+fn cargo_extension() {
+    use crate::types::{BuildTarget, CargoBuildTarget, RustEdition};
+
+    let text = r#"{"id":{"uri":"file:///a"},"tags":[],"capabilities":{"canCompile":true,"canTest":false,"canRun":false,"canDebug":false},"languageIds":["rust"],"dependencies":[],"dataKind":"cargo","data":{"edition":"2021","requiredFeatures":["std"]}}"#;
+    let mut target: BuildTarget = serde_json::from_str(text).unwrap();
+    let data: CargoBuildTarget = target.typed_data().unwrap().unwrap();
+    assert_eq!(data.edition, RustEdition::E2021);
+    assert!(data.required_features.contains("std"));
+
+    target.data_kind = Some("jvm".into());
+    assert!(target.typed_data::<CargoBuildTarget>().is_none());
+    target.set_typed_data(&data).unwrap();
+    assert_eq!(target.data_kind.as_deref(), Some("cargo"));
+
+    let text = r#"{"jsonrpc":"2.0","id":1,"method":"workspace/setCargoFeatures","params":{"packageId":"a 0.1.0","features":["default","std"]}}"#;
+    let msg: Message = serde_json::from_str(text).unwrap();
+    assert!(
+        matches!(msg, Message::Request(Request::WorkspaceSetCargoFeatures(_, params))
+        if params.package_id() == "a 0.1.0" && params.features().len() == 2)
+    );
+}
+
+#[test]
+fn jvm_extension() {
+    use crate::types::{JvmBuildTarget, JvmEnvironmentItem, JvmMainClass, JvmRunEnvironmentResult};
+
+    let data: JvmBuildTarget =
+        serde_json::from_str(r#"{"javaHome":"file:///usr/lib/jvm/17","javaVersion":"17"}"#)
+            .unwrap();
+    assert_eq!(data.java_version.as_deref(), Some("17"));
+
+    let text = r#"{"jsonrpc":"2.0","id":1,"method":"buildTarget/jvmRunEnvironment","params":{"targets":[{"uri":"file:///a"}],"originId":"run"}}"#;
+    let msg: Message = serde_json::from_str(text).unwrap();
+    assert!(
+        matches!(msg, Message::Request(Request::BuildTargetJvmRunEnvironment(_, params))
+        if params.targets().len() == 1 && params.origin_id().map(String::as_str) == Some("run"))
+    );
+
+    let item = JvmEnvironmentItem::new(
+        crate::types::BuildTargetIdentifier::new("file:///a".parse().unwrap()),
+        vec!["file:///a.jar".into()],
+        vec!["-Xmx2g".into()],
+        "/tmp".into(),
+        [("JAVA_OPTS".to_string(), "-ea".to_string())].into(),
+        Some(vec![JvmMainClass::new("a.Main".into(), vec![])]),
+    );
+    assert_eq!(
+        serde_json::to_value(JvmRunEnvironmentResult::new(vec![item])).unwrap(),
+        serde_json::json!({ "items": [{
+            "target": { "uri": "file:///a" },
+            "classpath": ["file:///a.jar"],
+            "jvmOptions": ["-Xmx2g"],
+            "workingDirectory": "/tmp",
+            "environmentVariables": { "JAVA_OPTS": "-ea" },
+            "mainClasses": [{ "className": "a.Main", "arguments": [] }],
+        }]})
+    );
+}
+
+#[test]
+fn custom_method_names_are_owned() {
+    use std::borrow::Cow;
+
+    let text = r#"{"id":1,"method":"custom/ask","params":null}"#;
+    let msg: Message = serde_json::from_str(text).unwrap();
+    assert!(matches!(
+        &msg,
+        Message::Request(Request::Custom(_, Cow::Owned(_), _))
+    ));
+    if let Message::Request(req) = &msg {
+        assert_eq!(req.method(), "custom/ask");
+    }
+
+    let req = Request::from((1.into(), "custom/ask", serde_json::Value::Null));
+    assert!(matches!(
+        req,
+        Request::Custom(_, Cow::Borrowed("custom/ask"), _)
+    ));
+}
+
+#[test]
+fn params_follow_method_registry() {
+    for info in crate::methods::METHODS {
+        let text = match info.is_request() {
+            true => format!("{{\"id\":1,\"method\":\"{}\"}}", info.method()),
+            false => format!("{{\"method\":\"{}\"}}", info.method()),
+        };
+        let msg = serde_json::from_str::<Message>(&text);
+        assert_eq!(msg.is_err(), info.params_required(), "{}", text);
+        if let Ok(msg) = msg {
+            assert_eq!(serde_json::to_string(&msg).unwrap(), text);
+        }
+    }
+
+    let text = "{\"id\":1,\"method\":\"custom/ask\"}";
+    let msg: Message = serde_json::from_str(text).unwrap();
+    assert_eq!(serde_json::to_string(&msg).unwrap(), text);
+}
+
+#[test]
+fn invalid_params_name_expected_type() {
+    let text = "{\"id\":1,\"method\":\"buildTarget/compile\",\"params\":{}}";
+    let err = serde_json::from_str::<Message>(text).unwrap_err();
+    let err = err.to_string();
+    assert!(
+        err.contains("invalid params for buildTarget/compile"),
+        "{}",
+        err
+    );
+    assert!(err.contains("BuildTargetCompile"), "{}", err);
+}
+
 #[test]
 fn serialize_request_with_null_params() {
     let msg = Message::Request(Request::Shutdown(3.into()));
@@ -25,3 +174,1275 @@ fn serialize_request_with_null_params() {
 
     assert_eq!("{\"id\":3,\"method\":\"build/shutdown\"}", serialized);
 }
+
+mod serve {
+    use crate::types::*;
+    use crate::{Connection, ErrorCode, LifecycleState, Message, Notification, Request, Response};
+    use std::thread;
+
+    struct TestServer;
+
+    impl BuildServer for TestServer {
+        fn initialize(&self, _params: InitializeBuild) -> anyhow::Result<InitializeBuildResult> {
+            Ok(InitializeBuildResult::new_simple(
+                "TestServer",
+                "0.1",
+                "2.0",
+                ServerCapabilities::default(),
+            ))
+        }
+
+        fn workspace_bts(&self) -> anyhow::Result<WorkspaceBuildTargetsResult> {
+            anyhow::bail!("no workspace loaded")
+        }
+    }
+
+    fn initialize_params() -> InitializeBuild {
+        InitializeBuild::new_simple(
+            "TestClient",
+            "0.1",
+            "2.0",
+            Url::parse("file:///tmp/workspace").unwrap(),
+            Default::default(),
+        )
+    }
+
+    /// Initialize result advertising `buildTarget/test` and `workspace/reload`.
+    fn test_provider_result() -> InitializeBuildResult {
+        let mut capabilities = ServerCapabilities::default();
+        capabilities.set_test_provider(Some(TestProvider::default()));
+        capabilities.set_can_reload(Some(true));
+        InitializeBuildResult::new_simple("TestServer", "0.1", "2.0", capabilities)
+    }
+
+    fn recv_response(client: &Connection) -> Response {
+        match client.receiver.recv().unwrap() {
+            Message::Response(resp) => resp,
+            msg => panic!("expected response, got {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn lifecycle() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || {
+            server.serve(TestServer).unwrap();
+            (server.state(), server.exit_code())
+        });
+
+        // Notifications before initialize are dropped, requests rejected.
+        client.send(Notification::Initialized).unwrap();
+        client
+            .send((0.into(), BuildTargetSources::new(vec![])))
+            .unwrap();
+        let resp = recv_response(&client);
+        assert_eq!(resp.id, Some(0.into()));
+        assert_eq!(
+            resp.error.unwrap().code,
+            ErrorCode::ServerNotInitialized as i32
+        );
+
+        client.send((1.into(), initialize_params())).unwrap();
+        let resp = recv_response(&client);
+        assert_eq!(resp.id, Some(1.into()));
+        assert_eq!(resp.result.unwrap()["displayName"], "TestServer");
+        client.send(Notification::Initialized).unwrap();
+
+        let params = BuildTargetSources::new(vec![]);
+        client.send((2.into(), params)).unwrap();
+        let resp = recv_response(&client);
+        assert_eq!(resp.id, Some(2.into()));
+        assert_eq!(resp.result.unwrap(), serde_json::json!({ "items": [] }));
+
+        client
+            .send(Request::WorkspaceBuildTargets(3.into()))
+            .unwrap();
+        let resp = recv_response(&client);
+        assert_eq!(resp.error.unwrap().message, "no workspace loaded");
+
+        client.send(Request::Shutdown(4.into())).unwrap();
+        assert_eq!(recv_response(&client).id, Some(4.into()));
+        client
+            .send((5.into(), BuildTargetSources::new(vec![])))
+            .unwrap();
+        let resp = recv_response(&client);
+        assert_eq!(resp.id, Some(5.into()));
+        assert_eq!(resp.error.unwrap().code, ErrorCode::InvalidRequest as i32);
+        client.send(Notification::Exit).unwrap();
+
+        let (state, exit_code) = handle.join().unwrap();
+        assert_eq!(state, LifecycleState::Exited);
+        assert_eq!(exit_code, 0);
+    }
+
+    #[test]
+    fn rejected_requests_are_not_pending() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || {
+            server.serve(TestServer).unwrap();
+            server
+        });
+        client.send((1.into(), initialize_params())).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        // Not advertised by `TestServer`.
+        client.send(Request::WorkspaceReload(2.into())).unwrap();
+        let resp = recv_response(&client);
+        assert_eq!(resp.error.unwrap().code, ErrorCode::MethodNotFound as i32);
+        client.send(Notification::CancelRequest(2.into())).unwrap();
+
+        client.send(Request::Shutdown(3.into())).unwrap();
+        recv_response(&client);
+        client
+            .send((4.into(), BuildTargetSources::new(vec![])))
+            .unwrap();
+        let resp = recv_response(&client);
+        assert_eq!(resp.error.unwrap().code, ErrorCode::InvalidRequest as i32);
+        client.send(Notification::Exit).unwrap();
+
+        let server = handle.join().unwrap();
+        assert!(server.pending.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn recv_checks_lifecycle() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || {
+            server.initialize(|_| test_provider_result()).unwrap();
+            loop {
+                match server.recv().unwrap() {
+                    Message::Request(req) if server.handle_shutdown(&req).unwrap() => {
+                        return server.exit_code();
+                    }
+                    Message::Request(req) => {
+                        server.send(Response::ok(req.id().clone(), ())).unwrap()
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        client
+            .send((0.into(), BuildTargetSources::new(vec![])))
+            .unwrap();
+        let resp = recv_response(&client);
+        assert_eq!(
+            resp.error.unwrap().code,
+            ErrorCode::ServerNotInitialized as i32
+        );
+        client.send((1.into(), initialize_params())).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        // Not advertised by `test_provider_result`.
+        let compile = BuildTargetCompile::new_simple(vec![]);
+        client
+            .send(Request::BuildTargetCompile(2.into(), compile))
+            .unwrap();
+        let resp = recv_response(&client);
+        assert_eq!(resp.error.unwrap().code, ErrorCode::MethodNotFound as i32);
+        client.send(Request::WorkspaceReload(3.into())).unwrap();
+        assert!(recv_response(&client).error.is_none());
+
+        client.send(Request::Shutdown(4.into())).unwrap();
+        assert_eq!(recv_response(&client).id, Some(4.into()));
+        client.send(Notification::Exit).unwrap();
+        assert_eq!(handle.join().unwrap(), 0);
+    }
+
+    #[test]
+    fn exit_before_initialize() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || {
+            server.serve(TestServer).unwrap();
+            server.exit_code()
+        });
+
+        client.send(Notification::Exit).unwrap();
+        assert_eq!(handle.join().unwrap(), 1);
+    }
+
+    enum Ask {}
+
+    impl crate::BspRequest for Ask {
+        const METHOD: &'static str = "custom/ask";
+        type Params = String;
+        type Result = String;
+    }
+
+    fn recv_request(client: &Connection) -> Request {
+        match client.receiver.recv().unwrap() {
+            Message::Request(req) => req,
+            msg => panic!("expected request, got {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn requests_to_client() {
+        let (server, client) = Connection::memory();
+        let server = std::sync::Arc::new(server);
+        let handle = {
+            let server = server.clone();
+            thread::spawn(move || server.serve(TestServer))
+        };
+        client.send((1.into(), initialize_params())).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        let pending = server.send_request::<Ask>("name".into()).unwrap();
+        let req = recv_request(&client);
+        assert_eq!(req.method(), "custom/ask");
+        client
+            .send(Response::ok(req.id().clone(), "client"))
+            .unwrap();
+        assert_eq!(pending.wait().unwrap(), "client");
+
+        // The serve loop is idle, sending the request has to wake it up to expire it.
+        server.set_request_timeout(Some(std::time::Duration::from_millis(10)));
+        let pending = server.send_request::<Ask>("late".into()).unwrap();
+        let id = recv_request(&client).id().clone();
+        match pending.wait() {
+            Err(crate::ExtractError::ResponseError(err)) => {
+                assert_eq!(err.code, ErrorCode::RequestCanceled as i32)
+            }
+            result => panic!("expected timeout, got {:?}", result),
+        }
+        assert!(matches!(
+            client.receiver.recv().unwrap(),
+            Message::Notification(Notification::CancelRequest(cancelled)) if cancelled == id
+        ));
+
+        server.set_request_timeout(None);
+        let pending = server.send_request::<Ask>("never".into()).unwrap();
+        recv_request(&client);
+        client.send(Notification::Exit).unwrap();
+        handle.join().unwrap().unwrap();
+        match pending.wait() {
+            Err(crate::ExtractError::ResponseError(err)) => {
+                assert_eq!(err.code, ErrorCode::InternalError as i32)
+            }
+            result => panic!("expected failure, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn messages_left_after_serve() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || {
+            let result = server.serve(TestServer);
+            (server, result)
+        });
+
+        client.send((1.into(), initialize_params())).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        // Answering fails once the client stops reading, which ends serving.
+        let sender = client.sender.clone();
+        drop(client);
+        sender
+            .send(Request::WorkspaceBuildTargets(2.into()).into())
+            .unwrap();
+        let (server, result) = handle.join().unwrap();
+        assert!(result.is_err());
+
+        sender.send(Notification::Exit.into()).unwrap();
+        assert!(matches!(
+            server.receiver.try_recv(),
+            Ok(Message::Notification(Notification::Exit))
+        ));
+    }
+
+    #[test]
+    fn disconnect_before_exit_fails() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || server.serve(TestServer));
+
+        client.send((1.into(), initialize_params())).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+        drop(client);
+        let err = handle.join().unwrap().unwrap_err();
+        assert_eq!(err.to_string(), "client disconnected before exit");
+    }
+
+    struct PanickingServer;
+
+    impl BuildServer for PanickingServer {
+        fn initialize(&self, params: InitializeBuild) -> anyhow::Result<InitializeBuildResult> {
+            TestServer.initialize(params)
+        }
+
+        fn workspace_bts(&self) -> anyhow::Result<WorkspaceBuildTargetsResult> {
+            panic!("no targets")
+        }
+    }
+
+    #[test]
+    fn panicking_handler_is_internal_error() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || server.serve(PanickingServer));
+
+        client.send((1.into(), initialize_params())).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        client
+            .send(Request::WorkspaceBuildTargets(2.into()))
+            .unwrap();
+        let error = recv_response(&client).error.unwrap();
+        assert_eq!(error.code, ErrorCode::InternalError as i32);
+        assert_eq!(error.message, "request handler panicked");
+
+        client.send(Request::Shutdown(3.into())).unwrap();
+        assert_eq!(recv_response(&client).id, Some(3.into()));
+        client.send(Notification::Exit).unwrap();
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn bsp_version_negotiation() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || {
+            server.serve(TestServer).unwrap();
+            server.bsp_version()
+        });
+
+        let mut params = initialize_params();
+        params.set_bsp_version("2.1.0".into());
+        client.send((1.into(), params)).unwrap();
+        let result = recv_response(&client).result.unwrap();
+        assert_eq!(result["bspVersion"], "2.0.0");
+        client.send(Notification::Initialized).unwrap();
+        client.send(Notification::Exit).unwrap();
+        assert_eq!(
+            handle.join().unwrap(),
+            Some(crate::BspVersion::new(2, 0, 0))
+        );
+
+        // Pre-release versions are negotiated, versions that do not fit are not rejected and
+        // the server answers with its own.
+        for (client_version, expected) in
+            [("2.1.0-M4", "2.0.0"), ("1.0.0", "2.0"), ("latest", "2.0")]
+        {
+            let (server, client) = Connection::memory();
+            let handle = thread::spawn(move || server.serve(TestServer));
+            let mut params = initialize_params();
+            params.set_bsp_version(client_version.into());
+            client.send((1.into(), params)).unwrap();
+            let result = recv_response(&client).result.unwrap();
+            assert_eq!(result["bspVersion"], expected, "{client_version}");
+            client.send(Notification::Initialized).unwrap();
+            client.send(Notification::Exit).unwrap();
+            handle.join().unwrap().unwrap();
+        }
+    }
+
+    #[test]
+    fn custom_request_is_method_not_found() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || server.serve(TestServer));
+
+        client.send((1.into(), initialize_params())).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        client
+            .send((2.into(), "custom/method", serde_json::Value::Null))
+            .unwrap();
+        let error = recv_response(&client).error.unwrap();
+        assert_eq!(error.code, crate::ErrorCode::MethodNotFound as i32);
+
+        client.send(Notification::Exit).unwrap();
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn unimplemented_method_is_method_not_found() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || server.serve(TestServer));
+
+        client.send((1.into(), initialize_params())).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        let target = BuildTargetIdentifier::new(Url::parse("file:///tmp/workspace").unwrap());
+        client
+            .send((2.into(), BuildTargetCompile::new_simple(vec![target])))
+            .unwrap();
+        let error = recv_response(&client).error.unwrap();
+        assert_eq!(error.code, crate::ErrorCode::MethodNotFound as i32);
+
+        client.send(Notification::Exit).unwrap();
+        handle.join().unwrap().unwrap();
+    }
+
+    struct RunServer;
+
+    impl BuildServer for RunServer {
+        fn initialize(&self, _params: InitializeBuild) -> anyhow::Result<InitializeBuildResult> {
+            Ok(InitializeBuildResult::default())
+        }
+
+        fn bt_run(
+            &self,
+            _params: BuildTargetRun,
+            _token: &CancellationToken,
+        ) -> anyhow::Result<BuildTargetRunResult> {
+            Ok(BuildTargetRunResult::new(None, StatusCode::Ok))
+        }
+    }
+
+    fn run_unadvertised(check_capabilities: bool) -> Response {
+        let (mut server, client) = Connection::memory();
+        server.set_check_capabilities(check_capabilities);
+        let handle = thread::spawn(move || {
+            server.serve(RunServer).unwrap();
+            server.capabilities()
+        });
+
+        client.send((1.into(), initialize_params())).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        let target = BuildTargetIdentifier::new(Url::parse("file:///tmp/workspace").unwrap());
+        client
+            .send((2.into(), BuildTargetRun::new_simple(target)))
+            .unwrap();
+        let resp = recv_response(&client);
+
+        client.send(Notification::Exit).unwrap();
+        let capabilities = handle.join().unwrap();
+        assert_eq!(capabilities.is_some(), check_capabilities);
+        resp
+    }
+
+    #[test]
+    fn unadvertised_capability_is_method_not_found() {
+        let error = run_unadvertised(true).error.unwrap();
+        assert_eq!(error.code, crate::ErrorCode::MethodNotFound as i32);
+        assert_eq!(error.message, "server does not support buildTarget/run");
+
+        assert!(run_unadvertised(false).error.is_none());
+    }
+
+    struct PolyglotServer;
+
+    fn polyglot_target(name: &str) -> BuildTargetIdentifier {
+        BuildTargetIdentifier::new(Url::parse(&format!("file:///tmp/workspace/{name}")).unwrap())
+    }
+
+    impl BuildServer for PolyglotServer {
+        fn initialize(&self, _params: InitializeBuild) -> anyhow::Result<InitializeBuildResult> {
+            Ok(InitializeBuildResult::default())
+        }
+
+        fn workspace_bts(&self) -> anyhow::Result<WorkspaceBuildTargetsResult> {
+            let target = |name: &str, language: &str| {
+                let mut target = BuildTarget::new_simple(
+                    polyglot_target(name),
+                    vec![],
+                    Default::default(),
+                    vec![],
+                    vec![],
+                );
+                target.language_ids = vec![language.to_string()];
+                target
+            };
+            Ok(WorkspaceBuildTargetsResult {
+                targets: vec![target("lib", "rust"), target("app", "scala")],
+            })
+        }
+
+        fn bt_sources(
+            &self,
+            _params: BuildTargetSources,
+        ) -> anyhow::Result<BuildTargetSourcesResult> {
+            Ok(BuildTargetSourcesResult::new(vec![
+                SourcesCollection::new(polyglot_target("lib"), vec![]),
+                SourcesCollection::new(polyglot_target("app"), vec![]),
+            ]))
+        }
+    }
+
+    #[test]
+    fn targets_filtered_by_client_languages() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || server.serve(PolyglotServer));
+
+        let mut params = initialize_params();
+        params.set_capabilities(ClientCapabilities::new(vec!["rust".into()]));
+        client.send((1.into(), params)).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        client
+            .send(Request::WorkspaceBuildTargets(2.into()))
+            .unwrap();
+        let result = recv_response(&client).result.unwrap();
+        let targets = result["targets"].as_array().unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0]["id"]["uri"], "file:///tmp/workspace/lib");
+
+        client.send(Notification::Exit).unwrap();
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn sources_filtered_without_build_targets() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || server.serve(PolyglotServer));
+
+        let mut params = initialize_params();
+        params.set_capabilities(ClientCapabilities::new(vec!["rust".into()]));
+        client.send((1.into(), params)).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        client
+            .send((2.into(), BuildTargetSources::new(vec![])))
+            .unwrap();
+        let result = recv_response(&client).result.unwrap();
+        let items = result["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["target"]["uri"], "file:///tmp/workspace/lib");
+
+        client.send(Notification::Exit).unwrap();
+        handle.join().unwrap().unwrap();
+    }
+
+    /// [`PolyglotServer`] counting its `workspace/buildTargets` calls.
+    struct CountingServer(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    impl BuildServer for CountingServer {
+        fn initialize(&self, params: InitializeBuild) -> anyhow::Result<InitializeBuildResult> {
+            PolyglotServer.initialize(params)
+        }
+
+        fn workspace_bts(&self) -> anyhow::Result<WorkspaceBuildTargetsResult> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            PolyglotServer.workspace_bts()
+        }
+
+        fn bt_sources(
+            &self,
+            params: BuildTargetSources,
+        ) -> anyhow::Result<BuildTargetSourcesResult> {
+            PolyglotServer.bt_sources(params)
+        }
+    }
+
+    #[test]
+    fn targets_refreshed_once_per_change() {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let count = || calls.load(std::sync::atomic::Ordering::SeqCst);
+        let (server, client) = Connection::memory();
+        let server = std::sync::Arc::new(server);
+        let serving = server.clone();
+        let counting = CountingServer(calls.clone());
+        let handle = thread::spawn(move || serving.serve(counting));
+
+        let mut params = initialize_params();
+        params.set_capabilities(ClientCapabilities::new(vec!["rust".into()]));
+        client.send((1.into(), params)).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        let sources = |id: i32| {
+            client
+                .send((id.into(), BuildTargetSources::new(vec![])))
+                .unwrap();
+            recv_response(&client).result.unwrap()["items"]
+                .as_array()
+                .unwrap()
+                .len()
+        };
+        assert_eq!(sources(2), 1);
+        assert_eq!(sources(3), 1);
+        assert_eq!(count(), 1);
+
+        let change = BuildTargetDidChange {
+            changes: vec![BuildTargetEvent::new_simple(polyglot_target("lib"))],
+        };
+        server
+            .send(Notification::BuildTargetDidChange(change))
+            .unwrap();
+        client.receiver.recv().unwrap();
+        assert_eq!(sources(4), 1);
+        assert_eq!(sources(5), 1);
+        assert_eq!(count(), 2);
+
+        client.send(Notification::Exit).unwrap();
+        handle.join().unwrap().unwrap();
+    }
+
+    struct CompileServer;
+
+    build_server! {
+        impl BuildServer for CompileServer {
+            fn initialize(&self, _params: InitializeBuild) -> anyhow::Result<InitializeBuildResult> {
+                let mut capabilities = ServerCapabilities::default();
+                capabilities.set_test_provider(Some(TestProvider::default()));
+                Ok(InitializeBuildResult::new_simple("CompileServer", "0.1", "2.0", capabilities))
+            }
+
+            fn bt_compile(
+                &self,
+                _params: BuildTargetCompile,
+                _token: &CancellationToken,
+            ) -> anyhow::Result<BuildTargetCompileResult> {
+                Ok(BuildTargetCompileResult::new_simple(StatusCode::Ok))
+            }
+
+            fn workspace_reload(&self) -> anyhow::Result<Option<serde_json::Value>> {
+                Ok(None)
+            }
+
+            fn workspace_cargo_features_state(&self) -> anyhow::Result<CargoFeaturesStateResult> {
+                Ok(CargoFeaturesStateResult::default())
+            }
+        }
+    }
+
+    #[test]
+    fn capabilities_derived_from_implemented_methods() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || server.serve(CompileServer));
+
+        let mut params = initialize_params();
+        params.set_capabilities(ClientCapabilities::new(vec!["rust".into()]));
+        client.send((1.into(), params)).unwrap();
+        let result = recv_response(&client).result.unwrap();
+        assert_eq!(
+            result["capabilities"],
+            serde_json::json!({
+                "compileProvider": { "languageIds": ["rust"] },
+                "canReload": true,
+                "cargoFeaturesProvider": true,
+            })
+        );
+        client.send(Notification::Initialized).unwrap();
+
+        client.send(Notification::Exit).unwrap();
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn cargo_features_derived_from_either_method() {
+        let mut capabilities = ServerCapabilities::default();
+        capabilities.derive_from_methods(&["workspace_set_cargo_features"], &[]);
+        assert_eq!(capabilities.cargo_features_provider(), Some(true));
+        capabilities.derive_from_methods(&["bt_compile"], &[]);
+        assert_eq!(capabilities.cargo_features_provider(), None);
+    }
+
+    struct CancellableServer(crossbeam_channel::Sender<()>);
+
+    impl BuildServer for CancellableServer {
+        fn initialize(&self, _params: InitializeBuild) -> anyhow::Result<InitializeBuildResult> {
+            Ok(test_provider_result())
+        }
+
+        fn bt_test(
+            &self,
+            _params: BuildTargetTest,
+            token: &CancellationToken,
+        ) -> anyhow::Result<BuildTargetTestResult> {
+            token.track_task("test-task".into());
+            self.0.send(()).unwrap();
+            while !token.is_cancelled() {
+                thread::yield_now();
+            }
+            Ok(BuildTargetTestResult::new_simple(StatusCode::Ok))
+        }
+    }
+
+    #[test]
+    fn cancel_request() {
+        let (started_sender, started) = crossbeam_channel::unbounded();
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || server.serve(CancellableServer(started_sender)));
+
+        client.send((1.into(), initialize_params())).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+
+        let target = BuildTargetIdentifier::new(Url::parse("file:///tmp/workspace").unwrap());
+        client
+            .send((2.into(), BuildTargetTest::new_simple(vec![target])))
+            .unwrap();
+        started.recv().unwrap();
+        client.send(Notification::CancelRequest(2.into())).unwrap();
+
+        match client.receiver.recv().unwrap() {
+            Message::Notification(Notification::TaskFinish(finish)) => {
+                assert_eq!(finish.task_id, "test-task".into());
+                assert!(finish.status.is_cancelled());
+            }
+            msg => panic!("expected task finish, got {:?}", msg),
+        }
+        let resp = recv_response(&client);
+        assert_eq!(resp.id, Some(2.into()));
+        assert_eq!(
+            resp.error.unwrap().code,
+            crate::ErrorCode::RequestCanceled as i32
+        );
+
+        client.send(Notification::Exit).unwrap();
+        handle.join().unwrap().unwrap();
+    }
+
+    struct BlockingServer(crossbeam_channel::Receiver<()>);
+
+    impl BuildServer for BlockingServer {
+        fn initialize(&self, _params: InitializeBuild) -> anyhow::Result<InitializeBuildResult> {
+            Ok(test_provider_result())
+        }
+
+        fn bt_test(
+            &self,
+            _params: BuildTargetTest,
+            _token: &CancellationToken,
+        ) -> anyhow::Result<BuildTargetTestResult> {
+            self.0.recv()?;
+            Ok(BuildTargetTestResult::new_simple(StatusCode::Ok))
+        }
+    }
+
+    fn serve_concurrent() -> (
+        Connection,
+        crossbeam_channel::Sender<()>,
+        thread::JoinHandle<Result<(), crate::ProtocolError>>,
+    ) {
+        let (release, released) = crossbeam_channel::unbounded();
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || server.serve_concurrent(BlockingServer(released), 4));
+
+        client.send((1.into(), initialize_params())).unwrap();
+        recv_response(&client);
+        client.send(Notification::Initialized).unwrap();
+        (client, release, handle)
+    }
+
+    fn test_request(id: i32) -> Message {
+        let target = BuildTargetIdentifier::new(Url::parse("file:///tmp/workspace").unwrap());
+        (id.into(), BuildTargetTest::new_simple(vec![target])).into()
+    }
+
+    #[test]
+    fn concurrent_queries_do_not_wait_for_tests() {
+        let (client, release, handle) = serve_concurrent();
+
+        client.send(test_request(2)).unwrap();
+        client
+            .send(Request::WorkspaceBuildTargets(3.into()))
+            .unwrap();
+        assert_eq!(recv_response(&client).id, Some(3.into()));
+
+        release.send(()).unwrap();
+        assert_eq!(recv_response(&client).id, Some(2.into()));
+
+        client.send(Request::Shutdown(4.into())).unwrap();
+        assert_eq!(recv_response(&client).id, Some(4.into()));
+        client.send(Notification::Exit).unwrap();
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn concurrent_reload_is_sequential() {
+        let (client, release, handle) = serve_concurrent();
+
+        client.send(test_request(2)).unwrap();
+        client.send(Request::WorkspaceReload(3.into())).unwrap();
+        client
+            .send(Request::WorkspaceBuildTargets(4.into()))
+            .unwrap();
+        let timeout = std::time::Duration::from_millis(100);
+        assert!(client.receiver.recv_timeout(timeout).is_err());
+
+        release.send(()).unwrap();
+        assert_eq!(recv_response(&client).id, Some(2.into()));
+        assert_eq!(recv_response(&client).id, Some(3.into()));
+        assert_eq!(recv_response(&client).id, Some(4.into()));
+
+        client.send(test_request(5)).unwrap();
+        client.send(Request::Shutdown(6.into())).unwrap();
+        release.send(()).unwrap();
+        assert_eq!(recv_response(&client).id, Some(5.into()));
+        assert_eq!(recv_response(&client).id, Some(6.into()));
+        client.send(Notification::Exit).unwrap();
+        handle.join().unwrap().unwrap();
+    }
+}
+
+#[cfg(unix)]
+mod process {
+    use crate::types::BuildTargetSources;
+    use crate::{Connection, LifecycleState, Message, Request, Response};
+    use std::process::Command;
+
+    fn echo(conn: &Connection, id: i32) {
+        conn.send((id.into(), BuildTargetSources::new(vec![])))
+            .unwrap();
+        let msg = conn.receiver.recv().unwrap();
+        assert!(
+            matches!(msg, Message::Request(Request::BuildTargetSources(i, _)) if i == id.into())
+        );
+    }
+
+    #[test]
+    fn spawned_connection_receives_responses() {
+        let (conn, _io_threads, mut process) = Connection::spawn(Command::new("cat")).unwrap();
+        conn.send(Response::ok(1.into(), ())).unwrap();
+        match conn.recv().unwrap() {
+            Message::Response(resp) => assert_eq!(resp.id, Some(1.into())),
+            msg => panic!("expected response, got {:?}", msg),
+        }
+        assert_eq!(conn.state(), LifecycleState::Uninitialized);
+
+        drop(conn);
+        assert!(process.wait().unwrap().success());
+    }
+
+    #[test]
+    fn spawn_kill_restart() {
+        let (conn, _io_threads, mut process) = Connection::spawn(Command::new("cat")).unwrap();
+        echo(&conn, 1);
+        assert!(process.try_wait().unwrap().is_none());
+
+        let status = process.kill().unwrap();
+        assert!(!status.success());
+        assert!(conn.receiver.recv().is_err());
+
+        let (conn, io_threads) = process.restart().unwrap();
+        echo(&conn, 2);
+
+        drop(conn);
+        assert!(process.wait().unwrap().success());
+        io_threads.join().unwrap();
+    }
+}
+
+#[test]
+fn install_connection_file() {
+    use crate::types::BspConnectionDetails;
+
+    let root = std::env::temp_dir().join(format!("bsp-server-install-{}", std::process::id()));
+    let argv = vec!["my-server".into(), "--bsp".into()];
+    let details = BspConnectionDetails::new("MyServer", "0.1", "2.0", vec!["rust".into()], argv);
+
+    let path = crate::install_connection_file(&root, &details).unwrap();
+    assert_eq!(path, root.join(".bsp/MyServer.json"));
+    let written: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(
+        written,
+        serde_json::json!({
+            "name": "MyServer",
+            "version": "0.1",
+            "bspVersion": "2.0",
+            "languages": ["rust"],
+            "argv": ["my-server", "--bsp"],
+        })
+    );
+
+    for name in ["", "..", "../escape", "sub/server", "sub\\server"] {
+        let details = BspConnectionDetails::new(name, "0.1", "2.0", vec![], vec![]);
+        let err = crate::install_connection_file(&root, &details).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{name:?}");
+    }
+    assert!(!root.join("escape.json").exists());
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(unix)]
+mod unix {
+    use crate::types::BuildTargetSources;
+    use crate::{Connection, Message, Request};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    #[test]
+    fn listen_and_connect() {
+        let dir = std::env::temp_dir().join(format!("bsp-server-unix-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bsp.sock");
+        // Leave a stale socket behind, as a crashed server would.
+        drop(UnixListener::bind(&path).unwrap());
+
+        let server = {
+            let path = path.clone();
+            thread::spawn(move || {
+                let (conn, _io_threads) = Connection::listen_unix(path).unwrap();
+                conn.receiver.recv().unwrap()
+            })
+        };
+        let client = loop {
+            match Connection::connect_unix(&path) {
+                Ok((conn, _io_threads)) => break conn,
+                Err(_) => thread::yield_now(),
+            }
+        };
+        client
+            .send((1.into(), BuildTargetSources::new(vec![])))
+            .unwrap();
+        let msg = server.join().unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(
+            matches!(msg, Message::Request(Request::BuildTargetSources(id, _)) if id == 1.into())
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn socket_private_before_accept() {
+        let dir = std::env::temp_dir().join(format!("bsp-server-private-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bsp.sock");
+
+        let _listener = Connection::bind_unix(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let entries: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn listen_keeps_other_files() {
+        let dir = std::env::temp_dir().join(format!("bsp-server-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bsp.sock");
+        std::fs::write(&path, "not a socket").unwrap();
+
+        let err = Connection::listen_unix(&path).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a socket");
+        let entries: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn listen_in_use() {
+        let dir = std::env::temp_dir().join(format!("bsp-server-in-use-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bsp.sock");
+        let _listener = UnixListener::bind(&path).unwrap();
+
+        let err = Connection::listen_unix(&path).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn from_io() {
+    use crate::{Connection, Notification, Response};
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut input = vec![];
+    let mut writer = crate::framing::FrameWriter::new(&mut input);
+    let params = crate::types::BuildTargetSources::new(vec![]);
+    Message::from(Request::BuildTargetSources(1.into(), params))
+        .write(&mut writer)
+        .unwrap();
+    Message::from(Notification::Exit)
+        .write(&mut writer)
+        .unwrap();
+    // Never read, the reader stops at exit.
+    Message::from(Notification::Initialized)
+        .write(&mut writer)
+        .unwrap();
+    drop(writer);
+
+    let output = SharedBuf::default();
+    let (conn, io_threads) = Connection::from_io(io::Cursor::new(input), output.clone());
+    let msg = conn.receiver.recv().unwrap();
+    assert!(matches!(msg, Message::Request(Request::BuildTargetSources(id, _)) if id == 1.into()));
+    conn.send(Response::ok(1.into(), ())).unwrap();
+    assert!(matches!(
+        conn.receiver.recv().unwrap(),
+        Message::Notification(Notification::Exit)
+    ));
+    assert!(conn.receiver.recv().is_err());
+
+    drop(conn);
+    io_threads.join().unwrap();
+    let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(
+        output,
+        "Content-Length: 38\r\n\r\n{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":null}"
+    );
+}
+
+mod invalid {
+    use crate::framing::FrameReader;
+    use crate::{Connection, ErrorCode, Message, Notification, Request, Response};
+    use std::io;
+
+    fn frame(body: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    fn invalid(msg: Option<Message>) -> crate::InvalidMessage {
+        match msg {
+            Some(Message::Invalid(invalid)) => invalid,
+            msg => panic!("expected invalid message, got {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn read_skips_bad_frames() {
+        let exit = frame(r#"{"jsonrpc":"2.0","method":"build/exit"}"#);
+        let input = [
+            frame("{not json"),
+            frame(r#"{"jsonrpc":"2.0","id":7,"method":"buildTarget/sources","params":1}"#),
+            frame(r#"{"jsonrpc":"2.0","method":"build/taskStart","params":1}"#),
+            "Content-Length: 2\r\nX-Broken\r\n\r\n{}".to_string(),
+            // The body is longer than announced, its tail is skipped up to the next frame.
+            "Content-Length: 2\r\n\r\n{}}\r\n".to_string() + &exit,
+        ]
+        .concat();
+        let mut input = FrameReader::new(io::Cursor::new(input), Default::default());
+        let mut read = || Message::read(&mut input).unwrap();
+
+        let parse = invalid(read());
+        assert_eq!(
+            (parse.code, parse.id.clone()),
+            (ErrorCode::ParseError, None)
+        );
+        let resp = parse.response().unwrap();
+        assert_eq!(
+            serde_json::to_value(&resp).unwrap()["id"],
+            serde_json::Value::Null
+        );
+
+        let params = invalid(read());
+        assert_eq!(params.code, ErrorCode::InvalidParams);
+        assert_eq!(params.response().unwrap().id, Some(7.into()));
+
+        let notification = invalid(read());
+        assert_eq!(notification.code, ErrorCode::InvalidParams);
+        assert!(notification.response().is_none());
+
+        assert_eq!(invalid(read()).code, ErrorCode::ParseError);
+        assert_eq!(invalid(read()).code, ErrorCode::InvalidRequest);
+        assert!(matches!(
+            read(),
+            Some(Message::Notification(Notification::Exit))
+        ));
+        assert!(read().is_none());
+    }
+
+    #[test]
+    fn response_with_null_id() {
+        let resp: Response =
+            serde_json::from_str(r#"{"id":null,"error":{"code":-32700,"message":"bad"}}"#).unwrap();
+        assert_eq!(resp.id, None);
+        assert!(serde_json::from_str::<Response>(r#"{"result":null}"#).is_err());
+
+        let msg: Message = serde_json::from_str(r#"{"method":"build/initialized"}"#).unwrap();
+        assert!(matches!(
+            msg,
+            Message::Notification(Notification::Initialized)
+        ));
+    }
+
+    #[test]
+    fn transport_answers_parse_errors() {
+        let params = r#"{"targets":[]}"#;
+        let request = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"buildTarget/sources","params":{params}}}"#
+        );
+        let input = frame("{not json") + &frame(&request);
+        let (writer, output) = shared_writer();
+        let (conn, io_threads) = Connection::from_io(io::Cursor::new(input), writer);
+
+        assert_eq!(
+            invalid(conn.receiver.recv().ok()).code,
+            ErrorCode::ParseError
+        );
+        assert!(matches!(
+            conn.receiver.recv().unwrap(),
+            Message::Request(Request::BuildTargetSources(..))
+        ));
+        drop(conn);
+        io_threads.join().unwrap();
+
+        let output = output.lock().unwrap();
+        let output = String::from_utf8_lossy(&output);
+        assert!(output.contains(r#""id":null,"error":{"code":-32700"#));
+    }
+
+    /// A writer whose output can be inspected once the connection is done.
+    fn shared_writer() -> (
+        impl io::Write + Send,
+        std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+    ) {
+        struct Shared(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+        impl io::Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let output = std::sync::Arc::default();
+        (Shared(std::sync::Arc::clone(&output)), output)
+    }
+}
+
+mod extract {
+    use crate::methods::{self, BspNotification, BspRequest};
+    use crate::types::*;
+    use crate::{ExtractError, Notification, Request, Response};
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct FormatParams {
+        file: String,
+    }
+
+    enum Format {}
+
+    impl BspRequest for Format {
+        const METHOD: &'static str = "custom/format";
+        type Params = FormatParams;
+        type Result = Vec<String>;
+    }
+
+    fn sources() -> BuildTargetSources {
+        let target = BuildTargetIdentifier::new(Url::parse("file:///tmp/workspace").unwrap());
+        BuildTargetSources::new(vec![target])
+    }
+
+    #[test]
+    fn methods_match_variants() {
+        let requests = [
+            (Request::Shutdown(1.into()), methods::Shutdown::METHOD),
+            (
+                Request::WorkspaceBuildTargets(1.into()),
+                methods::WorkspaceBuildTargets::METHOD,
+            ),
+            (
+                Request::WorkspaceReload(1.into()),
+                methods::WorkspaceReload::METHOD,
+            ),
+            (
+                Request::BuildTargetSources(1.into(), sources()),
+                methods::BuildTargetSources::METHOD,
+            ),
+        ];
+        for (req, method) in requests {
+            assert_eq!(req.method(), method);
+        }
+        let notifications = [
+            (Notification::Exit, methods::Exit::METHOD),
+            (Notification::Initialized, methods::Initialized::METHOD),
+            (
+                Notification::CancelRequest(1.into()),
+                methods::CancelRequest::METHOD,
+            ),
+            (TaskStart::new("task").into(), methods::TaskStart::METHOD),
+            (
+                BuildTargetDidChange::default().into(),
+                methods::BuildTargetDidChange::METHOD,
+            ),
+        ];
+        for (notification, method) in notifications {
+            assert_eq!(notification.method(), method);
+        }
+    }
+
+    #[test]
+    fn request() {
+        let req = Request::BuildTargetSources(2.into(), sources());
+        let req = match req.extract::<methods::Shutdown>() {
+            Err(ExtractError::MethodMismatch(req)) => req,
+            _ => panic!("expected method mismatch"),
+        };
+        let (id, params) = req.extract::<methods::BuildTargetSources>().unwrap();
+        assert_eq!(id, 2.into());
+        assert_eq!(params.targets().len(), 1);
+
+        let (id, ()) = Request::Shutdown(3.into())
+            .extract::<methods::Shutdown>()
+            .unwrap();
+        assert_eq!(id, 3.into());
+    }
+
+    #[test]
+    fn custom_request() {
+        let req = Request::Custom(4.into(), Format::METHOD.into(), json!({ "file": "a.rs" }));
+        assert_eq!(req.params().unwrap(), json!({ "file": "a.rs" }));
+        let (_, params) = req.extract::<Format>().unwrap();
+        assert_eq!(params.file, "a.rs");
+
+        let req = Request::Custom(4.into(), Format::METHOD.into(), json!({ "path": "a.rs" }));
+        assert!(matches!(
+            req.extract::<Format>(),
+            Err(ExtractError::JsonError { method, .. }) if method == Format::METHOD
+        ));
+    }
+
+    #[test]
+    fn notification() {
+        let notification: Notification = TaskStart::new("task").into();
+        let notification = match notification.extract::<methods::TaskFinish>() {
+            Err(ExtractError::MethodMismatch(notification)) => notification,
+            _ => panic!("expected method mismatch"),
+        };
+        let start = notification.extract::<methods::TaskStart>().unwrap();
+        assert_eq!(start.task_id, TaskId::from("task"));
+
+        let cancel = Notification::CancelRequest(5.into())
+            .extract::<methods::CancelRequest>()
+            .unwrap();
+        assert_eq!(cancel.id, 5.into());
+    }
+
+    #[test]
+    fn response() {
+        let resp = Response::ok(1.into(), vec!["a.rs"]);
+        assert_eq!(resp.extract::<Format>().unwrap(), vec!["a.rs".to_string()]);
+
+        let resp = Response::ok(1.into(), json!({ "items": 3 }));
+        assert!(matches!(
+            resp.extract::<Format>(),
+            Err(ExtractError::JsonError { .. })
+        ));
+
+        let resp = Response::method_not_found(1.into(), "nope".into());
+        assert!(matches!(
+            resp.extract::<Format>(),
+            Err(ExtractError::ResponseError(err)) if err.message == "nope"
+        ));
+
+        let resp = Response {
+            id: Some(1.into()),
+            result: None,
+            error: None,
+        };
+        assert!(resp.extract::<methods::Shutdown>().unwrap().is_none());
+    }
+}
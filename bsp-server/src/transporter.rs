@@ -1,81 +1,105 @@
-use std::io;
+use std::io::{self, BufRead, Write};
 use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::process::{ChildStderr, ChildStdin, ChildStdout};
 use std::thread;
 
+use crate::framing::{FrameReader, FrameWriter, Framing};
 use crate::Notification;
 
 use super::{IoThreads, Message};
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::{bounded, never, select, unbounded, Receiver, Sender};
 
 pub struct Transporter(pub Sender<Message>, pub Receiver<Message>, pub IoThreads);
 
 impl Transporter {
-    /// Creates an BSP connection via stdio.
-    pub fn stdio() -> Self {
+    /// Creates an BSP connection over any reader and writer pair.
+    ///
+    /// The reader thread stops at end of input or after forwarding `build/exit`, the writer thread
+    /// once every sender of the connection is dropped. Messages that can't be decoded are
+    /// answered with an error response, when the other side expects one, and forwarded as
+    /// [`Message::Invalid`].
+    pub fn new<R, W>(reader: R, writer: W, framing: Framing) -> Self
+    where
+        R: BufRead + Send + 'static,
+        W: Write + Send + 'static,
+    {
         let (writer_sender, writer_receiver) = bounded::<Message>(0);
+        // Error responses sent by the reader, kept apart so that the writer stops as soon as
+        // the connection is dropped.
+        let (error_sender, mut error_receiver) = unbounded::<Message>();
         let writer = thread::spawn(move || {
-            let stdout = io::stdout();
-            let mut stdout = stdout.lock();
-            writer_receiver
-                .into_iter()
-                .try_for_each(|it| it.write(&mut stdout))?;
-            Ok(())
+            let mut writer = FrameWriter::new(writer);
+            loop {
+                select! {
+                    recv(writer_receiver) -> msg => match msg {
+                        Ok(msg) => msg.write(&mut writer)?,
+                        Err(_) => return Ok(()),
+                    },
+                    recv(error_receiver) -> msg => match msg {
+                        Ok(msg) => msg.write(&mut writer)?,
+                        Err(_) => error_receiver = never(),
+                    },
+                }
+            }
         });
         let (reader_sender, reader_receiver) = bounded::<Message>(0);
         let reader = thread::spawn(move || {
-            let stdin = io::stdin();
-            let mut stdin = stdin.lock();
-            while let Some(msg) = Message::read(&mut stdin)? {
-                let is_exit = match &msg {
-                    Message::Notification(Notification::Exit) => true,
-                    _ => false,
-                };
+            let mut reader = FrameReader::new(reader, framing);
+            while let Some(msg) = Message::read(&mut reader)? {
+                let is_exit = matches!(&msg, Message::Notification(Notification::Exit));
 
-                reader_sender.send(msg).unwrap();
+                if let Message::Invalid(invalid) = &msg {
+                    tracing::warn!("received {}", invalid);
+                    if let Some(resp) = invalid.response() {
+                        let _ = error_sender.send(resp.into());
+                    }
+                }
 
-                if is_exit {
+                if reader_sender.send(msg).is_err() || is_exit {
                     break;
                 }
             }
             Ok(())
         });
-        let threads = IoThreads { reader, writer };
-        Self(writer_sender, reader_receiver, threads)
+        let io_threads = IoThreads::new(reader, writer);
+        Self(writer_sender, reader_receiver, io_threads)
+    }
+
+    /// Creates an BSP connection via stdio.
+    pub fn stdio() -> Self {
+        let stdin = io::BufReader::new(io::stdin());
+        Self::new(stdin, io::stdout(), Framing::default())
     }
 
     /// Creates an BSP connection via socket.
     pub fn socket(stream: TcpStream) -> Self {
-        let (reader_receiver, reader) = {
-            let stream = stream.try_clone().unwrap();
-            let (reader_sender, reader_receiver) = bounded::<Message>(0);
-            let reader = thread::spawn(move || {
-                let mut buf_read = io::BufReader::new(stream);
-                while let Some(msg) = Message::read(&mut buf_read).unwrap() {
-                    let is_exit = matches!(&msg, Message::Notification(Notification::Exit));
-                    reader_sender.send(msg).unwrap();
-                    if is_exit {
-                        break;
+        let reader = io::BufReader::new(stream.try_clone().unwrap());
+        Self::new(reader, stream, Framing::default())
+    }
+
+    /// Creates an BSP connection via unix domain socket.
+    #[cfg(unix)]
+    pub fn unix(stream: UnixStream) -> Self {
+        let reader = io::BufReader::new(stream.try_clone().unwrap());
+        Self::new(reader, stream, Framing::default())
+    }
+
+    /// Creates an BSP connection via the pipes of a spawned server process.
+    ///
+    /// Lines written by the server to stderr are forwarded to the log.
+    pub fn child(stdin: ChildStdin, stdout: ChildStdout, stderr: Option<ChildStderr>) -> Self {
+        if let Some(stderr) = stderr {
+            thread::spawn(move || {
+                for line in io::BufReader::new(stderr).lines() {
+                    match line {
+                        Ok(line) => tracing::info!("[server stderr] {}", line),
+                        Err(_) => break,
                     }
                 }
-                Ok(())
             });
-            (reader_receiver, reader)
-        };
-
-        let (writer_sender, writer) = {
-            let mut stream = stream.try_clone().unwrap();
-            let (writer_sender, writer_receiver) = bounded::<Message>(0);
-            let writer = thread::spawn(move || {
-                writer_receiver
-                    .into_iter()
-                    .try_for_each(|it| it.write(&mut stream))
-                    .unwrap();
-                Ok(())
-            });
-            (writer_sender, writer)
-        };
-
-        let io_threads = IoThreads::new(reader, writer);
-        Self(writer_sender, reader_receiver, io_threads)
+        }
+        Self::new(io::BufReader::new(stdout), stdin, Framing::default())
     }
 }
@@ -0,0 +1,127 @@
+use crate::ProtocolError;
+use std::fmt;
+use std::str::FromStr;
+
+/// A BSP version, compared following semver. Pre-release and build suffixes such as `2.1.0-M4`
+/// are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BspVersion {
+    major: u64,
+    minor: u64,
+    patch: u64,
+}
+
+impl BspVersion {
+    /// The oldest version this crate talks.
+    pub const MIN_SUPPORTED: BspVersion = BspVersion::new(2, 0, 0);
+    /// The newest version this crate talks.
+    pub const MAX_SUPPORTED: BspVersion = BspVersion::new(2, 2, 0);
+
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Get the major version.
+    pub fn major(&self) -> u64 {
+        self.major
+    }
+
+    /// Get the minor version.
+    pub fn minor(&self) -> u64 {
+        self.minor
+    }
+
+    /// Get the patch version.
+    pub fn patch(&self) -> u64 {
+        self.patch
+    }
+
+    /// Pick the version to talk with a client asking for `client` to a server implementing
+    /// `server`: the older of both, capped to [`BspVersion::MAX_SUPPORTED`].
+    ///
+    /// A server declaring no version talks whatever the client asks for. Fails if the major
+    /// versions differ or the result is out of the supported range.
+    pub fn negotiate(client: &str, server: &str) -> Result<BspVersion, ProtocolError> {
+        let client: BspVersion = client.parse()?;
+        let server = match server {
+            "" => Self::MAX_SUPPORTED,
+            server => server.parse()?,
+        };
+        if client.major != server.major {
+            return Err(ProtocolError(format!(
+                "client talks BSP {client}, incompatible with the server's BSP {server}"
+            )));
+        }
+        let version = client.min(server);
+        if version < Self::MIN_SUPPORTED || version.major > Self::MAX_SUPPORTED.major {
+            return Err(ProtocolError(format!(
+                "BSP {version} is not supported, supported versions are {} to {}",
+                Self::MIN_SUPPORTED,
+                Self::MAX_SUPPORTED
+            )));
+        }
+        Ok(version.min(Self::MAX_SUPPORTED))
+    }
+}
+
+impl FromStr for BspVersion {
+    type Err = ProtocolError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ProtocolError(format!("invalid BSP version {s:?}"));
+        let core = s.trim().split(['-', '+']).next().unwrap_or_default();
+        let mut parts = core.split('.').map(|part| part.parse::<u64>());
+        let mut next = |required| match parts.next() {
+            Some(part) => part.map_err(|_| invalid()),
+            None if required => Err(invalid()),
+            None => Ok(0),
+        };
+        let version = Self::new(next(true)?, next(false)?, next(false)?);
+        match parts.next() {
+            Some(_) => Err(invalid()),
+            None => Ok(version),
+        }
+    }
+}
+
+impl fmt::Display for BspVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> BspVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(version("2"), BspVersion::new(2, 0, 0));
+        assert_eq!(version("2.1"), BspVersion::new(2, 1, 0));
+        assert_eq!(version("2.1.0-M4"), BspVersion::new(2, 1, 0));
+        assert_eq!(version("2.2.1+build").to_string(), "2.2.1");
+        for invalid in ["", "two", "2.x", "2.1.0.1"] {
+            assert!(invalid.parse::<BspVersion>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn negotiate() {
+        let negotiate =
+            |client, server| BspVersion::negotiate(client, server).map(|v| v.to_string());
+        assert_eq!(negotiate("2.1.0", "2.0.0").unwrap(), "2.0.0");
+        assert_eq!(negotiate("2.0", "2.2").unwrap(), "2.0.0");
+        assert_eq!(negotiate("2.9", "2.9").unwrap(), "2.2.0");
+        assert_eq!(negotiate("2.1", "").unwrap(), "2.1.0");
+        assert!(negotiate("1.0", "2.0").is_err());
+        assert!(negotiate("3.0", "3.0").is_err());
+    }
+}
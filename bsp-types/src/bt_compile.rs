@@ -1,53 +1,54 @@
-use super::BuildTargetIdentifier;
+use super::{BuildTargetIdentifier, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-/// The run request is sent from the client to the server to run a build target. The server
-/// communicates during the initialize handshake whether this method is supported or not.
+/// The compile build target request is sent from the client to the server to compile the given
+/// list of build targets. The server communicates during the initialize handshake whether this
+/// method is supported or not.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct BuildTargetCompile {
     /// A sequence of build targets to compile.
-    target: BuildTargetIdentifier,
+    targets: Vec<BuildTargetIdentifier>,
 
     /// A unique identifier generated by the client to identify this request.
     ///  * The server may include this id in triggered notifications or responses.
     #[serde(skip_serializing_if = "Option::is_none")]
     origin_id: Option<String>,
 
-    /// Optional arguments to the executed application.
+    /// Optional arguments to the compilation process.
     #[serde(skip_serializing_if = "Option::is_none")]
-    arguments: Option<String>,
+    arguments: Option<Vec<String>>,
 }
 
 impl BuildTargetCompile {
     pub fn new(
-        target: BuildTargetIdentifier,
+        targets: Vec<BuildTargetIdentifier>,
         origin_id: Option<String>,
-        arguments: Option<String>,
+        arguments: Option<Vec<String>>,
     ) -> Self {
         Self {
-            target,
+            targets,
             origin_id,
             arguments,
         }
     }
-    pub fn new_simple(target: BuildTargetIdentifier) -> Self {
+    pub fn new_simple(targets: Vec<BuildTargetIdentifier>) -> Self {
         Self {
-            target,
+            targets,
             origin_id: None,
             arguments: None,
         }
     }
 
-    /// Get a reference to the bsp btrun params's target.
-    pub fn target(&self) -> &BuildTargetIdentifier {
-        &self.target
+    /// Get a reference to the bsp btcompile params's targets.
+    pub fn targets(&self) -> &[BuildTargetIdentifier] {
+        self.targets.as_ref()
     }
 
-    /// Set the bsp btrun params's target.
-    pub fn set_target(&mut self, target: BuildTargetIdentifier) {
-        self.target = target;
+    /// Set the bsp btcompile params's targets.
+    pub fn set_targets(&mut self, targets: Vec<BuildTargetIdentifier>) {
+        self.targets = targets;
     }
 
     /// Get a reference to the bsp btrun params's origin id.
@@ -61,12 +62,12 @@ impl BuildTargetCompile {
     }
 
     /// Get a reference to the bsp btrun params's arguments.
-    pub fn arguments(&self) -> Option<&String> {
-        self.arguments.as_ref()
+    pub fn arguments(&self) -> Option<&[String]> {
+        self.arguments.as_deref()
     }
 
     /// Set the bsp btrun params's arguments.
-    pub fn set_arguments(&mut self, arguments: Option<String>) {
+    pub fn set_arguments(&mut self, arguments: Option<Vec<String>>) {
         self.arguments = arguments;
     }
 }
@@ -80,7 +81,7 @@ pub struct BuildTargetCompileResult {
     origin_id: Option<String>,
 
     /// A status code for the execution.
-    status_code: usize,
+    status_code: StatusCode,
 
     /// Kind of data to expect in the `data` field. If this field is not set, the kind of data is not specified.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -95,7 +96,7 @@ pub struct BuildTargetCompileResult {
 impl BuildTargetCompileResult {
     pub fn new(
         origin_id: Option<String>,
-        status_code: usize,
+        status_code: StatusCode,
         data_kind: Option<String>,
         data: Option<Value>,
     ) -> Self {
@@ -106,7 +107,7 @@ impl BuildTargetCompileResult {
             data,
         }
     }
-    pub fn new_simple(status_code: usize) -> Self {
+    pub fn new_simple(status_code: StatusCode) -> Self {
         Self {
             origin_id: None,
             status_code,
@@ -121,7 +122,7 @@ impl BuildTargetCompileResult {
     }
 
     /// Get the bsp btrun result's status code.
-    pub fn status_code(&self) -> usize {
+    pub fn status_code(&self) -> StatusCode {
         self.status_code
     }
 
@@ -131,7 +132,7 @@ impl BuildTargetCompileResult {
     }
 
     /// Set the bsp btrun result's status code.
-    pub fn set_status_code(&mut self, status_code: usize) {
+    pub fn set_status_code(&mut self, status_code: StatusCode) {
         self.status_code = status_code;
     }
 
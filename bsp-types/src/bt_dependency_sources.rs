@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 /// the sources of build target dependencies that are external to the workspace. The dependency
 /// sources response must not include source files that belong to a build target within the
 /// workspace, see buildTarget/sources.
-
+///
 /// The server communicates during the initialize handshake whether this method is supported or
 /// not. This method can for example be used by a language server on textDocument/definition to "Go
 /// to definition" from project sources to dependency sources.
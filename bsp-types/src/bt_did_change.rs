@@ -42,19 +42,14 @@ impl BuildTargetEvent {
     }
 }
 
-#[derive(Clone, Debug, serde_repr::Deserialize_repr, serde_repr::Serialize_repr)]
+#[derive(Clone, Default, Debug, serde_repr::Deserialize_repr, serde_repr::Serialize_repr)]
 #[repr(u16)]
 pub enum BuildTargetEventKind {
     /// The build target is new (default).
+    #[default]
     Created = 1,
     /// The build target has changed.
     Changed = 2,
     /// The build target has been deleted.
     Deleted = 3,
 }
-
-impl Default for BuildTargetEventKind {
-    fn default() -> Self {
-        Self::Created
-    }
-}
@@ -33,3 +33,19 @@ impl TextDocumentInverseSources {
 pub struct BuildTargetInverseSourcesResult {
     targets: Vec<BuildTargetIdentifier>,
 }
+
+impl BuildTargetInverseSourcesResult {
+    pub fn new(targets: Vec<BuildTargetIdentifier>) -> Self {
+        Self { targets }
+    }
+
+    /// Get a reference to the bsp inverse sources result's targets.
+    pub fn targets(&self) -> &[BuildTargetIdentifier] {
+        self.targets.as_ref()
+    }
+
+    /// Set the bsp inverse sources result's targets.
+    pub fn set_targets(&mut self, targets: Vec<BuildTargetIdentifier>) {
+        self.targets = targets;
+    }
+}
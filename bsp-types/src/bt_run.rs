@@ -1,4 +1,4 @@
-use super::BuildTargetIdentifier;
+use super::{BuildTargetIdentifier, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -17,7 +17,7 @@ pub struct BuildTargetRun {
 
     /// Optional arguments to the executed application.
     #[serde(skip_serializing_if = "Option::is_none")]
-    arguments: Option<String>,
+    arguments: Option<Vec<String>>,
 
     /// Kind of data to expect in the data field. If this field is not set, the kind of data is not specified.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -33,7 +33,7 @@ impl BuildTargetRun {
     pub fn new(
         target: BuildTargetIdentifier,
         origin_id: Option<String>,
-        arguments: Option<String>,
+        arguments: Option<Vec<String>>,
         data_kind: Option<String>,
         data: Option<Value>,
     ) -> Self {
@@ -76,12 +76,12 @@ impl BuildTargetRun {
     }
 
     /// Get a reference to the bsp btrun params's arguments.
-    pub fn arguments(&self) -> Option<&String> {
-        self.arguments.as_ref()
+    pub fn arguments(&self) -> Option<&[String]> {
+        self.arguments.as_deref()
     }
 
     /// Set the bsp btrun params's arguments.
-    pub fn set_arguments(&mut self, arguments: Option<String>) {
+    pub fn set_arguments(&mut self, arguments: Option<Vec<String>>) {
         self.arguments = arguments;
     }
 
@@ -115,11 +115,11 @@ pub struct BuildTargetRunResult {
     origin_id: Option<String>,
 
     /** A status code for the execution. */
-    status_code: usize,
+    status_code: StatusCode,
 }
 
 impl BuildTargetRunResult {
-    pub fn new(origin_id: Option<String>, status_code: usize) -> Self {
+    pub fn new(origin_id: Option<String>, status_code: StatusCode) -> Self {
         Self {
             origin_id,
             status_code,
@@ -132,7 +132,7 @@ impl BuildTargetRunResult {
     }
 
     /// Get the bsp btrun result's status code.
-    pub fn status_code(&self) -> usize {
+    pub fn status_code(&self) -> StatusCode {
         self.status_code
     }
 
@@ -142,7 +142,7 @@ impl BuildTargetRunResult {
     }
 
     /// Set the bsp btrun result's status code.
-    pub fn set_status_code(&mut self, status_code: usize) {
+    pub fn set_status_code(&mut self, status_code: StatusCode) {
         self.status_code = status_code;
     }
 }
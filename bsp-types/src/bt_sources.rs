@@ -46,6 +46,11 @@ impl BuildTargetSourcesResult {
     pub fn set_items(&mut self, items: Vec<SourcesCollection>) {
         self.items = items;
     }
+
+    /// Get a mutable reference to the bsp sources result's items.
+    pub fn items_mut(&mut self) -> &mut Vec<SourcesCollection> {
+        &mut self.items
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -140,17 +145,12 @@ impl Sources {
     }
 }
 
-#[derive(Debug, serde_repr::Deserialize_repr, serde_repr::Serialize_repr)]
+#[derive(Debug, Default, serde_repr::Deserialize_repr, serde_repr::Serialize_repr)]
 #[repr(u16)]
 pub enum SourceKind {
     /// The source item references a normal file.
+    #[default]
     File = 1,
     /// The source item references a directory.
     Directory = 2,
 }
-
-impl Default for SourceKind {
-    fn default() -> Self {
-        Self::File
-    }
-}
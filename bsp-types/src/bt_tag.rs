@@ -4,9 +4,10 @@ use serde::{Deserialize, Serialize};
 ///  - customize how the target should be translated into the client's project model.
 ///  - group together different but related targets in the user interface.
 ///  - display icons or colors in the user interface.
+///
 ///  Pre-defined tags are listed in `BuildTargetTag` but clients and servers
 ///  are free to define new tags for custom purposes.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum BuildTargetTag {
     /// Target contains re-usable functionality for downstream targets. May have
@@ -28,6 +29,7 @@ pub enum BuildTargetTag {
     /// but does not require the `canRun` build target capability.
     Benchmark,
     /// Target should be ignored by IDEs.
+    #[default]
     NoIDE,
     // Actions on the target such as build and test should only be invoked manually
     // and explicitly. For example, triggering a build on all targets in the workspace
@@ -37,12 +39,6 @@ pub enum BuildTargetTag {
     Custom(String),
 }
 
-impl Default for BuildTargetTag {
-    fn default() -> Self {
-        Self::NoIDE
-    }
-}
-
 impl BuildTargetTag {
     pub fn custom<S: Into<String>>(value: S) -> Self {
         Self::Custom(value.into())
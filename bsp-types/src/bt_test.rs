@@ -1,4 +1,4 @@
-use super::BuildTargetIdentifier;
+use super::{BuildTargetIdentifier, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -8,17 +8,17 @@ use serde_json::Value;
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct BuildTargetTest {
-    /// The build target to run.
-    target: BuildTargetIdentifier,
+    /// A sequence of build targets to test.
+    targets: Vec<BuildTargetIdentifier>,
 
     /// A unique identifier generated by the client to identify this request.
     ///  * The server may include this id in triggered notifications or responses.
     #[serde(skip_serializing_if = "Option::is_none")]
     origin_id: Option<String>,
 
-    /// Optional arguments to the executed application.
+    /// Optional arguments to the test execution.
     #[serde(skip_serializing_if = "Option::is_none")]
-    arguments: Option<String>,
+    arguments: Option<Vec<String>>,
 
     /// Kind of data to expect in the data field. If this field is not set, the kind of data is not specified.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -32,23 +32,23 @@ pub struct BuildTargetTest {
 
 impl BuildTargetTest {
     pub fn new(
-        target: BuildTargetIdentifier,
+        targets: Vec<BuildTargetIdentifier>,
         origin_id: Option<String>,
-        arguments: Option<String>,
+        arguments: Option<Vec<String>>,
         data_kind: Option<String>,
         data: Option<Value>,
     ) -> Self {
         Self {
-            target,
+            targets,
             origin_id,
             arguments,
             data_kind,
             data,
         }
     }
-    pub fn new_simple(target: BuildTargetIdentifier) -> Self {
+    pub fn new_simple(targets: Vec<BuildTargetIdentifier>) -> Self {
         Self {
-            target,
+            targets,
             origin_id: None,
             arguments: None,
             data_kind: None,
@@ -56,14 +56,14 @@ impl BuildTargetTest {
         }
     }
 
-    /// Get a reference to the bsp btrun params's target.
-    pub fn target(&self) -> &BuildTargetIdentifier {
-        &self.target
+    /// Get a reference to the bsp bttest params's targets.
+    pub fn targets(&self) -> &[BuildTargetIdentifier] {
+        self.targets.as_ref()
     }
 
-    /// Set the bsp btrun params's target.
-    pub fn set_target(&mut self, target: BuildTargetIdentifier) {
-        self.target = target;
+    /// Set the bsp bttest params's targets.
+    pub fn set_targets(&mut self, targets: Vec<BuildTargetIdentifier>) {
+        self.targets = targets;
     }
 
     /// Get a reference to the bsp btrun params's origin id.
@@ -77,12 +77,12 @@ impl BuildTargetTest {
     }
 
     /// Get a reference to the bsp btrun params's arguments.
-    pub fn arguments(&self) -> Option<&String> {
-        self.arguments.as_ref()
+    pub fn arguments(&self) -> Option<&[String]> {
+        self.arguments.as_deref()
     }
 
     /// Set the bsp btrun params's arguments.
-    pub fn set_arguments(&mut self, arguments: Option<String>) {
+    pub fn set_arguments(&mut self, arguments: Option<Vec<String>>) {
         self.arguments = arguments;
     }
 
@@ -116,7 +116,7 @@ pub struct BuildTargetTestResult {
     origin_id: Option<String>,
 
     /// A status code for the execution.
-    status_code: usize,
+    status_code: StatusCode,
 
     /// Kind of data to expect in the `data` field. If this field is not set, the kind of data is not specified.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -131,7 +131,7 @@ pub struct BuildTargetTestResult {
 impl BuildTargetTestResult {
     pub fn new(
         origin_id: Option<String>,
-        status_code: usize,
+        status_code: StatusCode,
         data_kind: Option<String>,
         data: Option<Value>,
     ) -> Self {
@@ -142,7 +142,7 @@ impl BuildTargetTestResult {
             data,
         }
     }
-    pub fn new_simple(status_code: usize) -> Self {
+    pub fn new_simple(status_code: StatusCode) -> Self {
         Self {
             origin_id: None,
             status_code,
@@ -157,7 +157,7 @@ impl BuildTargetTestResult {
     }
 
     /// Get the bsp btrun result's status code.
-    pub fn status_code(&self) -> usize {
+    pub fn status_code(&self) -> StatusCode {
         self.status_code
     }
 
@@ -167,7 +167,7 @@ impl BuildTargetTestResult {
     }
 
     /// Set the bsp btrun result's status code.
-    pub fn set_status_code(&mut self, status_code: usize) {
+    pub fn set_status_code(&mut self, status_code: StatusCode) {
         self.status_code = status_code;
     }
 
@@ -0,0 +1,42 @@
+use crate::TaskId;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Token handed to long running [`crate::BuildServer`] handlers, signaling that the client sent
+/// `$/cancelRequest` for the request being handled.
+///
+/// Handlers should poll [`CancellationToken::is_cancelled`] and return early once it is set. Tasks
+/// started on behalf of the request can be recorded with [`CancellationToken::track_task`], so
+/// that they are reported as cancelled if the handler does not finish them.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    tasks: Arc<Mutex<Vec<TaskId>>>,
+}
+
+impl CancellationToken {
+    /// Returns `true` if the request was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Mark the request as cancelled.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst)
+    }
+
+    /// Record a task started on behalf of the request.
+    pub fn track_task(&self, task_id: TaskId) {
+        self.tasks.lock().unwrap().push(task_id)
+    }
+
+    /// Forget a task previously recorded with [`CancellationToken::track_task`] once it finished.
+    pub fn finish_task(&self, task_id: &TaskId) {
+        self.tasks.lock().unwrap().retain(|id| id != task_id)
+    }
+
+    /// Take the tasks that were started but not finished.
+    pub fn take_tasks(&self) -> Vec<TaskId> {
+        std::mem::take(&mut *self.tasks.lock().unwrap())
+    }
+}
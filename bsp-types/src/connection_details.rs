@@ -0,0 +1,104 @@
+use super::Language;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// Connection file describing how to launch a build server, read by clients from
+/// `<workspace>/.bsp/<name>.json` or from the user and system BSP directories.
+///
+/// The server is started by running `argv` in the workspace root, after which client and server
+/// talk over the server's stdin/stdout.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BspConnectionDetails {
+    /// The name of the build tool.
+    name: String,
+    /// The version of the build tool.
+    version: String,
+    /// The bsp version of the build tool.
+    bsp_version: String,
+    /// A collection of languages supported by this BSP server.
+    languages: Vec<Language>,
+    /// Command arguments runnable via system processes to start a BSP server.
+    argv: Vec<String>,
+}
+
+impl BspConnectionDetails {
+    pub fn new<S: Into<String>>(
+        name: S,
+        version: S,
+        bsp_version: S,
+        languages: Vec<Language>,
+        argv: Vec<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            bsp_version: bsp_version.into(),
+            languages,
+            argv,
+        }
+    }
+
+    /// Get a reference to the bsp connection details's name.
+    pub fn name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    /// Set the bsp connection details's name.
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    /// Get a reference to the bsp connection details's version.
+    pub fn version(&self) -> &str {
+        self.version.as_ref()
+    }
+
+    /// Set the bsp connection details's version.
+    pub fn set_version(&mut self, version: String) {
+        self.version = version;
+    }
+
+    /// Get a reference to the bsp connection details's bsp version.
+    pub fn bsp_version(&self) -> &str {
+        self.bsp_version.as_ref()
+    }
+
+    /// Set the bsp connection details's bsp version.
+    pub fn set_bsp_version(&mut self, bsp_version: String) {
+        self.bsp_version = bsp_version;
+    }
+
+    /// Get a reference to the bsp connection details's languages.
+    pub fn languages(&self) -> &[Language] {
+        self.languages.as_ref()
+    }
+
+    /// Set the bsp connection details's languages.
+    pub fn set_languages(&mut self, languages: Vec<Language>) {
+        self.languages = languages;
+    }
+
+    /// Get a reference to the bsp connection details's argv.
+    pub fn argv(&self) -> &[String] {
+        self.argv.as_ref()
+    }
+
+    /// Set the bsp connection details's argv.
+    pub fn set_argv(&mut self, argv: Vec<String>) {
+        self.argv = argv;
+    }
+
+    /// The file name the connection details are stored under, `<name>.json`.
+    pub fn file_name(&self) -> String {
+        format!("{}.json", self.name)
+    }
+
+    /// Build the command starting the server, or `None` if `argv` is empty.
+    pub fn command(&self) -> Option<Command> {
+        let (program, args) = self.argv.split_first()?;
+        let mut command = Command::new(program);
+        command.args(args);
+        Some(command)
+    }
+}
@@ -12,10 +12,10 @@ use serde_json::Value;
 /// request it should act as follows:
 ///
 /// - For a request the response should be an error with code: -32002. The message can be picked by
-/// the server.
+///   the server.
 ///
 /// - Notifications should be dropped, except for the exit notification. This will allow the exit
-/// of a server without an initialize request.
+///   of a server without an initialize request.
 ///
 /// Until the server has responded to the initialize request with an [`crate::InitializeBuildResult`], the
 /// client must not send any additional requests or notifications to the server.
@@ -51,7 +51,7 @@ impl InitializeBuild {
             display_name: display_name.into(),
             version: version.into(),
             bsp_version: bsp_version.into(),
-            root_uri: root_uri.into(),
+            root_uri,
             capabilities,
             data: data.into(),
         }
@@ -68,7 +68,7 @@ impl InitializeBuild {
             display_name: display_name.into(),
             version: version.into(),
             bsp_version: bsp_version.into(),
-            root_uri: root_uri.into(),
+            root_uri,
             capabilities,
             data: None,
         }
@@ -91,6 +91,11 @@ impl InitializeBuildResult {
         &self.capabilities
     }
 
+    /// Get a mutable reference to the bsp initialize build result's capabilities.
+    pub fn capabilities_mut(&mut self) -> &mut ServerCapabilities {
+        &mut self.capabilities
+    }
+
     /// Set the bsp initialize build result's data.
     pub fn set_data(&mut self, data: Option<Value>) {
         self.data = data;
@@ -0,0 +1,400 @@
+//! The JVM extension of the protocol, for build servers of Java, Scala and Kotlin targets.
+//!
+//! `buildTarget/jvmRunEnvironment` and `buildTarget/jvmTestEnvironment` give clients what they
+//! need to start a JVM for a target themselves, typically to run or debug it from the editor,
+//! while `buildTarget/jvmCompileClasspath` lists the classpath the targets are compiled with.
+//!
+//! Build targets running on the JVM carry a [`JvmBuildTarget`] as their `data`.
+
+use crate::{BuildTargetData, BuildTargetIdentifier};
+use lsp_types::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// `BuildTarget.data` of the build targets running on the JVM, see
+/// [`crate::BuildTarget::set_typed_data`].
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JvmBuildTarget {
+    /// Uri representing absolute path to jdk.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub java_home: Option<Url>,
+
+    /// The java version this target is supposed to use, for example "1.8".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub java_version: Option<String>,
+}
+
+impl BuildTargetData for JvmBuildTarget {
+    const DATA_KIND: &'static str = "jvm";
+}
+
+/// The JVM run environment request is sent from the client to the server in order to gather
+/// information required to launch a Java process. This is useful when the client wants to
+/// control the Java process execution, for example to enable custom Java agents or launch a
+/// custom main class during unit testing or debugging.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JvmRunEnvironment {
+    targets: Vec<BuildTargetIdentifier>,
+
+    /// A unique identifier generated by the client to identify this request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    origin_id: Option<String>,
+}
+
+impl JvmRunEnvironment {
+    pub fn new(targets: Vec<BuildTargetIdentifier>, origin_id: Option<String>) -> Self {
+        Self { targets, origin_id }
+    }
+
+    pub fn new_simple(targets: Vec<BuildTargetIdentifier>) -> Self {
+        Self {
+            targets,
+            origin_id: None,
+        }
+    }
+
+    /// Get a reference to the bsp jvm run environment params's targets.
+    pub fn targets(&self) -> &[BuildTargetIdentifier] {
+        self.targets.as_ref()
+    }
+
+    /// Set the bsp jvm run environment params's targets.
+    pub fn set_targets(&mut self, targets: Vec<BuildTargetIdentifier>) {
+        self.targets = targets;
+    }
+
+    /// Get a mutable reference to the bsp jvm run environment params's targets.
+    pub fn targets_mut(&mut self) -> &mut Vec<BuildTargetIdentifier> {
+        &mut self.targets
+    }
+
+    /// Get a reference to the bsp jvm run environment params's origin id.
+    pub fn origin_id(&self) -> Option<&String> {
+        self.origin_id.as_ref()
+    }
+
+    /// Set the bsp jvm run environment params's origin id.
+    pub fn set_origin_id(&mut self, origin_id: Option<String>) {
+        self.origin_id = origin_id;
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JvmRunEnvironmentResult {
+    items: Vec<JvmEnvironmentItem>,
+}
+
+impl JvmRunEnvironmentResult {
+    pub fn new(items: Vec<JvmEnvironmentItem>) -> Self {
+        Self { items }
+    }
+
+    /// Get a reference to the bsp jvm run environment result's items.
+    pub fn items(&self) -> &[JvmEnvironmentItem] {
+        self.items.as_ref()
+    }
+
+    /// Get a mutable reference to the bsp jvm run environment result's items.
+    pub fn items_mut(&mut self) -> &mut Vec<JvmEnvironmentItem> {
+        &mut self.items
+    }
+}
+
+/// The JVM test environment request is sent from the client to the server in order to gather
+/// information required to launch a Java process for running the tests of a target, see
+/// [`JvmRunEnvironment`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JvmTestEnvironment {
+    targets: Vec<BuildTargetIdentifier>,
+
+    /// A unique identifier generated by the client to identify this request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    origin_id: Option<String>,
+}
+
+impl JvmTestEnvironment {
+    pub fn new(targets: Vec<BuildTargetIdentifier>, origin_id: Option<String>) -> Self {
+        Self { targets, origin_id }
+    }
+
+    pub fn new_simple(targets: Vec<BuildTargetIdentifier>) -> Self {
+        Self {
+            targets,
+            origin_id: None,
+        }
+    }
+
+    /// Get a reference to the bsp jvm test environment params's targets.
+    pub fn targets(&self) -> &[BuildTargetIdentifier] {
+        self.targets.as_ref()
+    }
+
+    /// Set the bsp jvm test environment params's targets.
+    pub fn set_targets(&mut self, targets: Vec<BuildTargetIdentifier>) {
+        self.targets = targets;
+    }
+
+    /// Get a mutable reference to the bsp jvm test environment params's targets.
+    pub fn targets_mut(&mut self) -> &mut Vec<BuildTargetIdentifier> {
+        &mut self.targets
+    }
+
+    /// Get a reference to the bsp jvm test environment params's origin id.
+    pub fn origin_id(&self) -> Option<&String> {
+        self.origin_id.as_ref()
+    }
+
+    /// Set the bsp jvm test environment params's origin id.
+    pub fn set_origin_id(&mut self, origin_id: Option<String>) {
+        self.origin_id = origin_id;
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JvmTestEnvironmentResult {
+    items: Vec<JvmEnvironmentItem>,
+}
+
+impl JvmTestEnvironmentResult {
+    pub fn new(items: Vec<JvmEnvironmentItem>) -> Self {
+        Self { items }
+    }
+
+    /// Get a reference to the bsp jvm test environment result's items.
+    pub fn items(&self) -> &[JvmEnvironmentItem] {
+        self.items.as_ref()
+    }
+
+    /// Get a mutable reference to the bsp jvm test environment result's items.
+    pub fn items_mut(&mut self) -> &mut Vec<JvmEnvironmentItem> {
+        &mut self.items
+    }
+}
+
+/// JVM environment to run or test a build target with.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JvmEnvironmentItem {
+    target: BuildTargetIdentifier,
+
+    /// The classpath entries, as uris of jars and class directories.
+    classpath: Vec<String>,
+
+    /// Options to pass to the JVM, for example "-Xmx2g".
+    jvm_options: Vec<String>,
+
+    /// The directory to start the JVM in.
+    working_directory: String,
+
+    /// Environment variables to set for the JVM.
+    environment_variables: BTreeMap<String, String>,
+
+    /// The main classes of the target, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    main_classes: Option<Vec<JvmMainClass>>,
+}
+
+impl JvmEnvironmentItem {
+    pub fn new(
+        target: BuildTargetIdentifier,
+        classpath: Vec<String>,
+        jvm_options: Vec<String>,
+        working_directory: String,
+        environment_variables: BTreeMap<String, String>,
+        main_classes: Option<Vec<JvmMainClass>>,
+    ) -> Self {
+        Self {
+            target,
+            classpath,
+            jvm_options,
+            working_directory,
+            environment_variables,
+            main_classes,
+        }
+    }
+
+    /// Get a reference to the bsp jvm environment item's target.
+    pub fn target(&self) -> &BuildTargetIdentifier {
+        &self.target
+    }
+
+    /// Set the bsp jvm environment item's target.
+    pub fn set_target(&mut self, target: BuildTargetIdentifier) {
+        self.target = target;
+    }
+
+    /// Get a reference to the bsp jvm environment item's classpath.
+    pub fn classpath(&self) -> &[String] {
+        self.classpath.as_ref()
+    }
+
+    /// Set the bsp jvm environment item's classpath.
+    pub fn set_classpath(&mut self, classpath: Vec<String>) {
+        self.classpath = classpath;
+    }
+
+    /// Get a reference to the bsp jvm environment item's jvm options.
+    pub fn jvm_options(&self) -> &[String] {
+        self.jvm_options.as_ref()
+    }
+
+    /// Set the bsp jvm environment item's jvm options.
+    pub fn set_jvm_options(&mut self, jvm_options: Vec<String>) {
+        self.jvm_options = jvm_options;
+    }
+
+    /// Get a reference to the bsp jvm environment item's working directory.
+    pub fn working_directory(&self) -> &str {
+        self.working_directory.as_ref()
+    }
+
+    /// Set the bsp jvm environment item's working directory.
+    pub fn set_working_directory(&mut self, working_directory: String) {
+        self.working_directory = working_directory;
+    }
+
+    /// Get a reference to the bsp jvm environment item's environment variables.
+    pub fn environment_variables(&self) -> &BTreeMap<String, String> {
+        &self.environment_variables
+    }
+
+    /// Set the bsp jvm environment item's environment variables.
+    pub fn set_environment_variables(&mut self, environment_variables: BTreeMap<String, String>) {
+        self.environment_variables = environment_variables;
+    }
+
+    /// Get a reference to the bsp jvm environment item's main classes.
+    pub fn main_classes(&self) -> Option<&[JvmMainClass]> {
+        self.main_classes.as_deref()
+    }
+
+    /// Set the bsp jvm environment item's main classes.
+    pub fn set_main_classes(&mut self, main_classes: Option<Vec<JvmMainClass>>) {
+        self.main_classes = main_classes;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JvmMainClass {
+    /// The fully qualified name of the main class.
+    class_name: String,
+
+    /// The arguments to pass to the main method.
+    arguments: Vec<String>,
+}
+
+impl JvmMainClass {
+    pub fn new(class_name: String, arguments: Vec<String>) -> Self {
+        Self {
+            class_name,
+            arguments,
+        }
+    }
+
+    /// Get a reference to the bsp jvm main class's class name.
+    pub fn class_name(&self) -> &str {
+        self.class_name.as_ref()
+    }
+
+    /// Set the bsp jvm main class's class name.
+    pub fn set_class_name(&mut self, class_name: String) {
+        self.class_name = class_name;
+    }
+
+    /// Get a reference to the bsp jvm main class's arguments.
+    pub fn arguments(&self) -> &[String] {
+        self.arguments.as_ref()
+    }
+
+    /// Set the bsp jvm main class's arguments.
+    pub fn set_arguments(&mut self, arguments: Vec<String>) {
+        self.arguments = arguments;
+    }
+}
+
+/// The JVM compile classpath request is sent from the client to the server to query for the
+/// classpath of the given build targets, as they are compiled.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JvmCompileClasspath {
+    targets: Vec<BuildTargetIdentifier>,
+}
+
+impl JvmCompileClasspath {
+    pub fn new(targets: Vec<BuildTargetIdentifier>) -> Self {
+        Self { targets }
+    }
+
+    /// Get a reference to the bsp jvm compile classpath params's targets.
+    pub fn targets(&self) -> &[BuildTargetIdentifier] {
+        self.targets.as_ref()
+    }
+
+    /// Set the bsp jvm compile classpath params's targets.
+    pub fn set_targets(&mut self, targets: Vec<BuildTargetIdentifier>) {
+        self.targets = targets;
+    }
+
+    /// Get a mutable reference to the bsp jvm compile classpath params's targets.
+    pub fn targets_mut(&mut self) -> &mut Vec<BuildTargetIdentifier> {
+        &mut self.targets
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JvmCompileClasspathResult {
+    items: Vec<JvmCompileClasspathItem>,
+}
+
+impl JvmCompileClasspathResult {
+    pub fn new(items: Vec<JvmCompileClasspathItem>) -> Self {
+        Self { items }
+    }
+
+    /// Get a reference to the bsp jvm compile classpath result's items.
+    pub fn items(&self) -> &[JvmCompileClasspathItem] {
+        self.items.as_ref()
+    }
+
+    /// Get a mutable reference to the bsp jvm compile classpath result's items.
+    pub fn items_mut(&mut self) -> &mut Vec<JvmCompileClasspathItem> {
+        &mut self.items
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JvmCompileClasspathItem {
+    target: BuildTargetIdentifier,
+
+    /// The classpath entries, as uris of jars and class directories.
+    classpath: Vec<String>,
+}
+
+impl JvmCompileClasspathItem {
+    pub fn new(target: BuildTargetIdentifier, classpath: Vec<String>) -> Self {
+        Self { target, classpath }
+    }
+
+    /// Get a reference to the bsp jvm compile classpath item's target.
+    pub fn target(&self) -> &BuildTargetIdentifier {
+        &self.target
+    }
+
+    /// Set the bsp jvm compile classpath item's target.
+    pub fn set_target(&mut self, target: BuildTargetIdentifier) {
+        self.target = target;
+    }
+
+    /// Get a reference to the bsp jvm compile classpath item's classpath.
+    pub fn classpath(&self) -> &[String] {
+        self.classpath.as_ref()
+    }
+
+    /// Set the bsp jvm compile classpath item's classpath.
+    pub fn set_classpath(&mut self, classpath: Vec<String>) {
+        self.classpath = classpath;
+    }
+}
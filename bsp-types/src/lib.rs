@@ -11,18 +11,23 @@ mod bt_run;
 mod bt_sources;
 mod bt_tag;
 mod bt_test;
+mod cancellation;
 mod client_capabilities;
+mod connection_details;
 mod debug_session_start;
 mod initialize_params;
 mod initialize_result;
+mod jvm;
 mod language;
 mod log_message;
 mod message_type;
 mod providers;
 mod publish_diagnostics;
+mod rust;
 mod server;
 mod server_capabilities;
 mod show_message;
+mod status_code;
 mod task;
 mod workspace_build_targets;
 
@@ -39,18 +44,23 @@ pub use bt_run::*;
 pub use bt_sources::*;
 pub use bt_tag::*;
 pub use bt_test::*;
+pub use cancellation::*;
 pub use client_capabilities::*;
+pub use connection_details::*;
 pub use debug_session_start::*;
 pub use initialize_params::*;
 pub use initialize_result::*;
+pub use jvm::*;
 pub use language::*;
 pub use log_message::*;
-pub use lsp_types::Url;
+pub use lsp_types::{Diagnostic, TextDocumentIdentifier, Url};
 pub use message_type::*;
 pub use providers::*;
 pub use publish_diagnostics::*;
+pub use rust::*;
 pub use server::*;
 pub use server_capabilities::*;
 pub use show_message::*;
+pub use status_code::*;
 pub use task::*;
 pub use workspace_build_targets::*;
@@ -38,7 +38,7 @@ impl LogMessage {
     ) -> Self {
         Self {
             typ,
-            task: task.map(Into::into),
+            task,
             origin_id: orid.map(Into::into),
             message: msg.into(),
         }
@@ -1,4 +1,4 @@
-#[derive(Clone, Debug, serde_repr::Deserialize_repr, serde_repr::Serialize_repr)]
+#[derive(Clone, Default, Debug, serde_repr::Deserialize_repr, serde_repr::Serialize_repr)]
 #[repr(u16)]
 pub enum MessageType {
     /// An error message.
@@ -8,11 +8,6 @@ pub enum MessageType {
     /// An information message.
     Info = 3,
     /// A log message. (default)
+    #[default]
     Log = 4,
 }
-
-impl Default for MessageType {
-    fn default() -> Self {
-        Self::Log
-    }
-}
@@ -25,3 +25,71 @@ pub struct PublishDiagnostics {
      * mapped to the same `textDocument` and `buildTarget`. */
     reset: bool,
 }
+
+impl PublishDiagnostics {
+    pub fn new(
+        text_document: TextDocumentIdentifier,
+        build_target: BuildTargetIdentifier,
+        origin_id: Option<String>,
+        diagnostics: Vec<Diagnostic>,
+        reset: bool,
+    ) -> Self {
+        Self {
+            text_document,
+            build_target,
+            origin_id,
+            diagnostics,
+            reset,
+        }
+    }
+
+    /// Get a reference to the publish diagnostics's text document.
+    pub fn text_document(&self) -> &TextDocumentIdentifier {
+        &self.text_document
+    }
+
+    /// Set the publish diagnostics's text document.
+    pub fn set_text_document(&mut self, text_document: TextDocumentIdentifier) {
+        self.text_document = text_document;
+    }
+
+    /// Get a reference to the publish diagnostics's build target.
+    pub fn build_target(&self) -> &BuildTargetIdentifier {
+        &self.build_target
+    }
+
+    /// Set the publish diagnostics's build target.
+    pub fn set_build_target(&mut self, build_target: BuildTargetIdentifier) {
+        self.build_target = build_target;
+    }
+
+    /// Get a reference to the publish diagnostics's origin id.
+    pub fn origin_id(&self) -> Option<&String> {
+        self.origin_id.as_ref()
+    }
+
+    /// Set the publish diagnostics's origin id.
+    pub fn set_origin_id(&mut self, origin_id: Option<String>) {
+        self.origin_id = origin_id;
+    }
+
+    /// Get a reference to the publish diagnostics's diagnostics.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.diagnostics.as_ref()
+    }
+
+    /// Set the publish diagnostics's diagnostics.
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics = diagnostics;
+    }
+
+    /// Get the publish diagnostics's reset.
+    pub fn reset(&self) -> bool {
+        self.reset
+    }
+
+    /// Set the publish diagnostics's reset.
+    pub fn set_reset(&mut self, reset: bool) {
+        self.reset = reset;
+    }
+}
@@ -0,0 +1,13 @@
+//! The Rust extension of the protocol, for build servers of Cargo workspaces.
+//!
+//! `buildTarget/rustWorkspace` describes the packages and targets of the workspace with enough
+//! detail for a client such as rust-analyzer to load it, while `workspace/cargoFeaturesState` and
+//! `workspace/setCargoFeatures` expose and change the Cargo features enabled in each package.
+//!
+//! Build targets of Cargo packages carry a [`CargoBuildTarget`] as their `data`.
+
+mod cargo;
+mod workspace;
+
+pub use cargo::*;
+pub use workspace::*;
@@ -0,0 +1,94 @@
+use crate::{BuildTargetIdentifier, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The cargo features state request is sent from the client to the server to query for the
+/// current state of the Cargo features. Provides also mapping between Cargo packages and build
+/// target identifiers.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CargoFeaturesStateResult {
+    /// The list of Cargo packages with assigned to them target identifiers and available
+    /// features.
+    pub packages_features: Vec<PackageFeatures>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageFeatures {
+    /// The Cargo package identifier.
+    pub package_id: String,
+
+    /// The list of build target identifiers assigned to the Cargo package.
+    pub targets: Vec<BuildTargetIdentifier>,
+
+    /// The list of available features for the Cargo package.
+    pub available_features: BTreeMap<String, BTreeSet<String>>,
+
+    /// The list of enabled features for the Cargo package.
+    pub enabled_features: BTreeSet<String>,
+}
+
+/// The enable cargo features request is sent from the client to the server to set provided
+/// features collection as a new state for the specified Cargo package.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SetCargoFeatures {
+    /// Package ID for which new features state will be set.
+    package_id: String,
+
+    /// The list of features to be set as a new state.
+    features: BTreeSet<String>,
+}
+
+impl SetCargoFeatures {
+    pub fn new(package_id: String, features: BTreeSet<String>) -> Self {
+        Self {
+            package_id,
+            features,
+        }
+    }
+
+    /// Get a reference to the bsp set cargo features params's package id.
+    pub fn package_id(&self) -> &str {
+        self.package_id.as_ref()
+    }
+
+    /// Set the bsp set cargo features params's package id.
+    pub fn set_package_id(&mut self, package_id: String) {
+        self.package_id = package_id;
+    }
+
+    /// Get a reference to the bsp set cargo features params's features.
+    pub fn features(&self) -> &BTreeSet<String> {
+        &self.features
+    }
+
+    /// Set the bsp set cargo features params's features.
+    pub fn set_features(&mut self, features: BTreeSet<String>) {
+        self.features = features;
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SetCargoFeaturesResult {
+    /// The status code of the operation.
+    status_code: StatusCode,
+}
+
+impl SetCargoFeaturesResult {
+    pub fn new(status_code: StatusCode) -> Self {
+        Self { status_code }
+    }
+
+    /// Get the bsp set cargo features result's status code.
+    pub fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    /// Set the bsp set cargo features result's status code.
+    pub fn set_status_code(&mut self, status_code: StatusCode) {
+        self.status_code = status_code;
+    }
+}
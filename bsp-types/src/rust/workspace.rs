@@ -0,0 +1,288 @@
+use crate::{BuildTargetData, BuildTargetIdentifier};
+use lsp_types::Url;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The Rust workspace request is sent from the client to the server to query for the information
+/// about the project build needed by the client to load the workspace, such as the packages and
+/// targets of the build targets and their dependencies.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RustWorkspace {
+    /// A sequence of build targets for workspace resolution.
+    targets: Vec<BuildTargetIdentifier>,
+}
+
+impl RustWorkspace {
+    pub fn new(targets: Vec<BuildTargetIdentifier>) -> Self {
+        Self { targets }
+    }
+
+    /// Get a reference to the bsp rust workspace params's targets.
+    pub fn targets(&self) -> &[BuildTargetIdentifier] {
+        self.targets.as_ref()
+    }
+
+    /// Set the bsp rust workspace params's targets.
+    pub fn set_targets(&mut self, targets: Vec<BuildTargetIdentifier>) {
+        self.targets = targets;
+    }
+
+    /// Get a mutable reference to the bsp rust workspace params's targets.
+    pub fn targets_mut(&mut self) -> &mut Vec<BuildTargetIdentifier> {
+        &mut self.targets
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RustWorkspaceResult {
+    /// Packages of given targets.
+    pub packages: Vec<RustPackage>,
+
+    /// Dependencies in `cargo metadata` as listed in the package `Cargo.toml`, without package
+    /// resolution or any additional data, keyed by package id.
+    pub raw_dependencies: BTreeMap<String, Vec<RustRawDependency>>,
+
+    /// Resolved dependencies of the build, keyed by package id.
+    pub dependencies: BTreeMap<String, Vec<RustDependency>>,
+
+    /// A sequence of build targets taken into consideration during build process.
+    pub resolved_targets: Vec<BuildTargetIdentifier>,
+}
+
+/// A `crate` is the smallest amount of code that the Rust compiler considers at a time. It can
+/// come in one of two forms: a binary crate or a library crate. A `package` is a bundle of one or
+/// more crates that provides a set of functionality.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RustPackage {
+    /// The package's unique identifier.
+    pub id: String,
+
+    /// The package's root path.
+    pub root_url: Url,
+
+    /// The name of the package.
+    pub name: String,
+
+    /// The version of the package.
+    pub version: String,
+
+    /// Defines the reason a package is in a project.
+    pub origin: RustPackageOrigin,
+
+    /// Code edition of the package.
+    pub edition: RustEdition,
+
+    /// The source ID of the dependency, for example:
+    /// `"registry+https://github.com/rust-lang/crates.io-index"`.
+    /// `None` for the workspace members.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+
+    /// Corresponds to source files which can be compiled into a crate from this package.
+    /// Contains only resolved targets without conflicts.
+    pub resolved_targets: Vec<RustTarget>,
+
+    /// Same as `resolved_targets`, but contains all targets from this package.
+    pub all_targets: Vec<RustTarget>,
+
+    /// Set of features defined for the package (including optional dependencies). Each feature
+    /// maps to an array of features or dependencies it enables. The entry named "default"
+    /// defines which features are enabled by default.
+    pub features: BTreeMap<String, BTreeSet<String>>,
+
+    /// Array of features enabled on this package.
+    pub enabled_features: BTreeSet<String>,
+
+    /// Conditional compilation flags that can be set based on certain conditions. They can be
+    /// used to enable or disable certain sections of code during the build process.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cfg_options: Option<BTreeMap<String, Vec<String>>>,
+
+    /// Environment variables for the package.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<BTreeMap<String, String>>,
+
+    /// Path to the directory where build scripts of the package put their outputs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_dir_url: Option<Url>,
+
+    /// File path to the compiled proc-macro library file of the package, if it is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proc_macro_artifact: Option<Url>,
+}
+
+/// Why a package is part of the workspace.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RustPackageOrigin {
+    /// External dependency of a workspace member.
+    Dependency,
+    /// Member of the workspace.
+    Workspace,
+    /// Part of the standard library.
+    Stdlib,
+    /// Dependency of the standard library.
+    StdlibDependency,
+}
+
+/// The Rust edition a package or target is compiled with.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RustEdition {
+    #[serde(rename = "2015")]
+    E2015,
+    #[serde(rename = "2018")]
+    E2018,
+    #[serde(rename = "2021")]
+    E2021,
+    #[serde(rename = "2024")]
+    E2024,
+}
+
+/// `RustTarget` contains data of the target as defined in Cargo metadata.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RustTarget {
+    /// The name of the target.
+    pub name: String,
+
+    /// Path to the root module of the crate.
+    pub crate_root_url: Url,
+
+    /// A target's kind.
+    pub kind: RustTargetKind,
+
+    /// Type of output that is produced by a crate during the build process. The crate type
+    /// determines how the source code is compiled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crate_types: Option<Vec<RustCrateType>>,
+
+    /// The Rust edition of the target.
+    pub edition: RustEdition,
+
+    /// Whether or not this target has doc tests enabled, and the target is compatible with doc
+    /// testing.
+    pub doctest: bool,
+
+    /// A sequence of required features.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_features: Option<BTreeSet<String>>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RustTargetKind {
+    /// For lib targets.
+    Lib = 1,
+    /// For binaries.
+    Bin = 2,
+    /// For integration tests.
+    Test = 3,
+    /// For examples.
+    Example = 4,
+    /// For benchmarks.
+    Bench = 5,
+    /// For build scripts.
+    CustomBuild = 6,
+    /// For unknown targets.
+    Unknown = 7,
+}
+
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RustCrateType {
+    Bin = 1,
+    Lib = 2,
+    Rlib = 3,
+    Dylib = 4,
+    Cdylib = 5,
+    Staticlib = 6,
+    ProcMacro = 7,
+    Unknown = 8,
+}
+
+/// A dependency as declared in the `Cargo.toml` of a package.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RustRawDependency {
+    /// The name of the dependency.
+    pub name: String,
+
+    /// Name to which this dependency is renamed when declared in Cargo.toml.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rename: Option<String>,
+
+    /// The dependency kind.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<RustDepKind>,
+
+    /// The target platform for the dependency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+
+    /// Indicates whether this is an optional dependency.
+    pub optional: bool,
+
+    /// Indicates whether default features are enabled.
+    pub uses_default_features: bool,
+
+    /// A sequence of enabled features.
+    pub features: BTreeSet<String>,
+}
+
+/// A resolved dependency of a package.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RustDependency {
+    /// The id of the package of the dependency.
+    pub pkg: String,
+
+    /// The name of the dependency's library target. If this is a renamed dependency, this is the
+    /// new name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Array of dependency kinds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dep_kinds: Option<Vec<RustDepKindInfo>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RustDepKindInfo {
+    /// The dependency kind.
+    pub kind: RustDepKind,
+
+    /// The target platform for the dependency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RustDepKind {
+    /// For old Cargo versions prior to `1.41.0`.
+    Unclassified,
+    /// For `[dependencies]`.
+    Normal,
+    /// For `[dev-dependencies]`.
+    Dev,
+    /// For `[build-dependencies]`.
+    Build,
+}
+
+/// `BuildTarget.data` of the build targets of Cargo packages, see [`crate::BuildTarget::set_typed_data`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CargoBuildTarget {
+    /// The Rust edition of the target.
+    pub edition: RustEdition,
+
+    /// Features the target requires to be built.
+    pub required_features: BTreeSet<String>,
+}
+
+impl BuildTargetData for CargoBuildTarget {
+    const DATA_KIND: &'static str = "cargo";
+}
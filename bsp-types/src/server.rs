@@ -2,6 +2,21 @@
 use super::*;
 use anyhow::Result;
 use serde_json::Value;
+use std::fmt;
+
+/// Error returned by [`BuildServer`] methods that the implementation does not override.
+///
+/// Servers answer requests failing with this error using `MethodNotFound` (-32601).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodNotFound(pub &'static str);
+
+impl fmt::Display for MethodNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "method not supported: {}", self.0)
+    }
+}
+
+impl std::error::Error for MethodNotFound {}
 
 pub trait BuildServer {
     /// Invoked when client sends server "build/initialize"
@@ -11,10 +26,10 @@ pub trait BuildServer {
     /// follows:
     ///
     /// - For a request the response should be an error with code: -32002. The message can be
-    /// picked by the server.
+    ///   picked by the server.
     ///
     /// - Notifications should be dropped, except for the exit notification. This will allow the
-    /// exit of a server without an initialize request.
+    ///   exit of a server without an initialize request.
     ///
     /// Until the server has responded to the initialize request with an [`InitializeBuildResult`],
     /// the client must not send any additional requests or notifications to the server.
@@ -79,8 +94,7 @@ pub trait BuildServer {
         &self,
         params: BuildTargetDependencyModules,
     ) -> Result<BuildTargetDependencyModulesResult> {
-        todo!()
-        // Err(Error::method_not_found())
+        Err(MethodNotFound("buildTarget/dependencyModules").into())
     }
 
     /// Invoked when client sends server "buildTarget/dependencyModules"
@@ -89,8 +103,7 @@ pub trait BuildServer {
     /// launches a Microsoft DAP server and returns a connection URI for the client to interact with.
     // #[rpc(name = "debugSession/start")]
     fn debug_session_start(&self, params: DebugSessionStart) -> Result<DebugSessionStartResult> {
-        todo!()
-        // Err(Error::method_not_found())
+        Err(MethodNotFound("debugSession/start").into())
     }
 
     /// Invoked when client sends server "buildTarget/sources"
@@ -146,20 +159,30 @@ pub trait BuildServer {
     ///
     /// The run request is sent from the client to the server to run a build target. The server
     /// communicates during the initialize handshake whether this method is supported or not.
+    ///
+    /// `token` is cancelled when the client sends `$/cancelRequest` for this request.
     // #[rpc(name = "buildTarget/run")]
-    fn bt_run(&self, params: BuildTargetRun) -> Result<BuildTargetRunResult> {
-        todo!()
-        // Err(Error::method_not_found())
+    fn bt_run(
+        &self,
+        params: BuildTargetRun,
+        token: &CancellationToken,
+    ) -> Result<BuildTargetRunResult> {
+        Err(MethodNotFound("buildTarget/run").into())
     }
 
     /// Invoked when client sends server "buildTarget/compile"
     ///
     /// The run request is sent from the client to the server to run a build target. The server
     /// communicates during the initialize handshake whether this method is supported or not.
+    ///
+    /// `token` is cancelled when the client sends `$/cancelRequest` for this request.
     // #[rpc(name = "buildTarget/compile")]
-    fn bt_compile(&self, params: BuildTargetCompile) -> Result<BuildTargetCompileResult> {
-        todo!()
-        // Err(Error::method_not_found())
+    fn bt_compile(
+        &self,
+        params: BuildTargetCompile,
+        token: &CancellationToken,
+    ) -> Result<BuildTargetCompileResult> {
+        Err(MethodNotFound("buildTarget/compile").into())
     }
 
     /// Invoked when client sends server "buildTarget/test"
@@ -167,10 +190,15 @@ pub trait BuildServer {
     /// The test build target request is sent from the client to the server to test the given list of
     /// build targets. The server communicates during the initialize handshake whether this method is
     /// supported or not.
+    ///
+    /// `token` is cancelled when the client sends `$/cancelRequest` for this request.
     // #[rpc(name = "buildTarget/test")]
-    fn bt_test(&self, params: BuildTargetTest) -> Result<BuildTargetTestResult> {
-        todo!()
-        // Err(Error::method_not_found())
+    fn bt_test(
+        &self,
+        params: BuildTargetTest,
+        token: &CancellationToken,
+    ) -> Result<BuildTargetTestResult> {
+        Err(MethodNotFound("buildTarget/test").into())
     }
 
     /// Invoked when client sends server "buildTarget/cleanCache"
@@ -185,7 +213,130 @@ pub trait BuildServer {
     /// results in a full compilation.
     // #[rpc(name = "buildTarget/cleanCache")]
     fn bt_clean_cache(&self, params: BuildTargetCleanCache) -> Result<BuildTargetCleanCacheResult> {
-        todo!()
-        // Err(Error::method_not_found())
+        Err(MethodNotFound("buildTarget/cleanCache").into())
+    }
+
+    /// Invoked when client sends server "buildTarget/jvmRunEnvironment"
+    ///
+    /// The JVM run environment request is sent from the client to the server in order to gather
+    /// information required to launch a Java process, such as the classpath, JVM options and
+    /// main classes of the given targets.
+    // #[rpc(name = "buildTarget/jvmRunEnvironment")]
+    fn bt_jvm_run_environment(&self, params: JvmRunEnvironment) -> Result<JvmRunEnvironmentResult> {
+        Err(MethodNotFound("buildTarget/jvmRunEnvironment").into())
+    }
+
+    /// Invoked when client sends server "buildTarget/jvmTestEnvironment"
+    ///
+    /// The JVM test environment request is sent from the client to the server in order to gather
+    /// information required to launch a Java process running the tests of the given targets.
+    // #[rpc(name = "buildTarget/jvmTestEnvironment")]
+    fn bt_jvm_test_environment(
+        &self,
+        params: JvmTestEnvironment,
+    ) -> Result<JvmTestEnvironmentResult> {
+        Err(MethodNotFound("buildTarget/jvmTestEnvironment").into())
+    }
+
+    /// Invoked when client sends server "buildTarget/jvmCompileClasspath"
+    ///
+    /// The JVM compile classpath request is sent from the client to the server to query for the
+    /// classpath the given targets are compiled with.
+    // #[rpc(name = "buildTarget/jvmCompileClasspath")]
+    fn bt_jvm_compile_classpath(
+        &self,
+        params: JvmCompileClasspath,
+    ) -> Result<JvmCompileClasspathResult> {
+        Err(MethodNotFound("buildTarget/jvmCompileClasspath").into())
+    }
+
+    /// Invoked when client sends server "buildTarget/rustWorkspace"
+    ///
+    /// The Rust workspace request is sent from the client to the server to query for the
+    /// information about the project build needed by the client to load the workspace, such as
+    /// packages, their dependencies and the features and cfg options they are built with.
+    // #[rpc(name = "buildTarget/rustWorkspace")]
+    fn bt_rust_workspace(&self, params: RustWorkspace) -> Result<RustWorkspaceResult> {
+        Err(MethodNotFound("buildTarget/rustWorkspace").into())
     }
+
+    /// Invoked when client sends server "workspace/cargoFeaturesState"
+    ///
+    /// The cargo features state request is sent from the client to the server to query for the
+    /// current state of the Cargo features of every package, along with the build targets of
+    /// each package.
+    // #[rpc(name = "workspace/cargoFeaturesState")]
+    fn workspace_cargo_features_state(&self) -> Result<CargoFeaturesStateResult> {
+        Err(MethodNotFound("workspace/cargoFeaturesState").into())
+    }
+
+    /// Invoked when client sends server "workspace/setCargoFeatures"
+    ///
+    /// The set cargo features request is sent from the client to the server to set the given
+    /// features as the new state of a Cargo package.
+    // #[rpc(name = "workspace/setCargoFeatures")]
+    fn workspace_set_cargo_features(
+        &self,
+        params: SetCargoFeatures,
+    ) -> Result<SetCargoFeaturesResult> {
+        Err(MethodNotFound("workspace/setCargoFeatures").into())
+    }
+
+    /// Names of the methods this implementation overrides.
+    ///
+    /// Generated by [`build_server!`](crate::build_server) and used to derive the
+    /// [`ServerCapabilities`] advertised on initialization. `None` leaves the capabilities
+    /// returned by [`BuildServer::initialize`] untouched.
+    fn implemented_methods(&self) -> Option<&'static [&'static str]> {
+        None
+    }
+}
+
+/// Implement [`BuildServer`] while recording which methods are overridden, so that the advertised
+/// [`ServerCapabilities`] can be derived from them.
+///
+/// # Example
+///
+/// ```
+/// use bsp_types::*;
+///
+/// struct Server;
+///
+/// build_server! {
+///     impl BuildServer for Server {
+///         fn initialize(&self, _params: InitializeBuild) -> anyhow::Result<InitializeBuildResult> {
+///             Ok(InitializeBuildResult::default())
+///         }
+///
+///         fn bt_compile(
+///             &self,
+///             _params: BuildTargetCompile,
+///             _token: &CancellationToken,
+///         ) -> anyhow::Result<BuildTargetCompileResult> {
+///             Ok(BuildTargetCompileResult::new_simple(StatusCode::Ok))
+///         }
+///     }
+/// }
+///
+/// assert_eq!(Server.implemented_methods(), Some(&["initialize", "bt_compile"][..]));
+/// ```
+#[macro_export]
+macro_rules! build_server {
+    (impl $($rest:tt)*) => {
+        $crate::build_server!(@header [impl] $($rest)*);
+    };
+    (@header [$($header:tt)*] {
+        $($(#[$attr:meta])* fn $name:ident $args:tt $(-> $ret:ty)? $body:block)*
+    }) => {
+        $($header)* {
+            $($(#[$attr])* fn $name $args $(-> $ret)? $body)*
+
+            fn implemented_methods(&self) -> Option<&'static [&'static str]> {
+                Some(&[$(stringify!($name)),*])
+            }
+        }
+    };
+    (@header [$($header:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::build_server!(@header [$($header)* $next] $($rest)*);
+    };
 }
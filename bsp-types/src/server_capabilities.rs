@@ -2,7 +2,7 @@ use super::providers::*;
 use serde::{Deserialize, Serialize};
 
 /// Server Capabilities
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ServerCapabilities {
     /// The languages the server supports compilation
@@ -54,6 +54,26 @@ pub struct ServerCapabilities {
     /// target change events via "buildTarget/didChange"
     #[serde(skip_serializing_if = "Option::is_none")]
     build_target_changed_provider: Option<bool>,
+
+    /// The server provides all the resolved information needed to run a JVM process via method
+    /// "buildTarget/jvmRunEnvironment"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jvm_run_environment_provider: Option<bool>,
+
+    /// The server provides all the resolved information needed to test a JVM target via method
+    /// "buildTarget/jvmTestEnvironment"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jvm_test_environment_provider: Option<bool>,
+
+    /// The server provides the classpath the targets are compiled with via method
+    /// "buildTarget/jvmCompileClasspath"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jvm_compile_classpath_provider: Option<bool>,
+
+    /// The server provides the state of Cargo features and can change it
+    /// via methods "workspace/cargoFeaturesState" and "workspace/setCargoFeatures"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cargo_features_provider: Option<bool>,
 }
 
 impl ServerCapabilities {
@@ -159,4 +179,102 @@ impl ServerCapabilities {
     pub fn build_target_changed_provider(&self) -> Option<bool> {
         self.build_target_changed_provider
     }
+
+    /// Set the bsp server capabilities's jvm run environment provider.
+    pub fn set_jvm_run_environment_provider(&mut self, jvm_run_environment_provider: Option<bool>) {
+        self.jvm_run_environment_provider = jvm_run_environment_provider;
+    }
+
+    /// Get the bsp server capabilities's jvm run environment provider.
+    pub fn jvm_run_environment_provider(&self) -> Option<bool> {
+        self.jvm_run_environment_provider
+    }
+
+    /// Set the bsp server capabilities's jvm test environment provider.
+    pub fn set_jvm_test_environment_provider(
+        &mut self,
+        jvm_test_environment_provider: Option<bool>,
+    ) {
+        self.jvm_test_environment_provider = jvm_test_environment_provider;
+    }
+
+    /// Get the bsp server capabilities's jvm test environment provider.
+    pub fn jvm_test_environment_provider(&self) -> Option<bool> {
+        self.jvm_test_environment_provider
+    }
+
+    /// Set the bsp server capabilities's jvm compile classpath provider.
+    pub fn set_jvm_compile_classpath_provider(
+        &mut self,
+        jvm_compile_classpath_provider: Option<bool>,
+    ) {
+        self.jvm_compile_classpath_provider = jvm_compile_classpath_provider;
+    }
+
+    /// Get the bsp server capabilities's jvm compile classpath provider.
+    pub fn jvm_compile_classpath_provider(&self) -> Option<bool> {
+        self.jvm_compile_classpath_provider
+    }
+
+    /// Set the bsp server capabilities's cargo features provider.
+    pub fn set_cargo_features_provider(&mut self, cargo_features_provider: Option<bool>) {
+        self.cargo_features_provider = cargo_features_provider;
+    }
+
+    /// Get the bsp server capabilities's cargo features provider.
+    pub fn cargo_features_provider(&self) -> Option<bool> {
+        self.cargo_features_provider
+    }
+
+    /// Restrict the capabilities to the given implemented [`crate::BuildServer`] methods.
+    ///
+    /// Providers for methods that are not implemented are removed. Providers for implemented
+    /// methods that were left unset are enabled, using `language_ids` for the language based ones.
+    pub fn derive_from_methods(&mut self, methods: &[&str], language_ids: &[String]) {
+        let has = |name: &str| methods.contains(&name);
+        let flag = |name: &str, value: Option<bool>| {
+            has(name).then(|| value.unwrap_or(true)).filter(|v| *v)
+        };
+
+        macro_rules! languages {
+            ($field:ident, $name:literal, $provider:ident) => {
+                self.$field = if has($name) {
+                    self.$field.take().or_else(|| {
+                        Some($provider {
+                            language_ids: language_ids.to_vec(),
+                        })
+                    })
+                } else {
+                    None
+                };
+            };
+        }
+
+        languages!(compile_provider, "bt_compile", CompileProvider);
+        languages!(test_provider, "bt_test", TestProvider);
+        languages!(run_provider, "bt_run", RunProvider);
+        languages!(debug_provider, "debug_session_start", DebugProvider);
+        self.inverse_sources_provider = flag("bt_inverse_sources", self.inverse_sources_provider);
+        self.dependency_sources_provider =
+            flag("bt_dependency_sources", self.dependency_sources_provider);
+        self.dependency_modules_provider =
+            flag("bt_dependency_modules", self.dependency_modules_provider);
+        self.resources_provider = flag("bt_resources", self.resources_provider);
+        self.can_reload = flag("workspace_reload", self.can_reload);
+        self.jvm_run_environment_provider =
+            flag("bt_jvm_run_environment", self.jvm_run_environment_provider);
+        self.jvm_test_environment_provider = flag(
+            "bt_jvm_test_environment",
+            self.jvm_test_environment_provider,
+        );
+        self.jvm_compile_classpath_provider = flag(
+            "bt_jvm_compile_classpath",
+            self.jvm_compile_classpath_provider,
+        );
+        let cargo_features =
+            has("workspace_cargo_features_state") || has("workspace_set_cargo_features");
+        self.cargo_features_provider = cargo_features
+            .then(|| self.cargo_features_provider.unwrap_or(true))
+            .filter(|v| *v);
+    }
 }
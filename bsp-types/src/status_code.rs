@@ -0,0 +1,37 @@
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+/// Status of a compile, test or run request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum StatusCode {
+    /// Execution was successful.
+    #[default]
+    Ok = 1,
+    /// Execution failed.
+    Error = 2,
+    /// Execution was cancelled.
+    Cancelled = 3,
+}
+
+impl StatusCode {
+    /// Returns `true` if the status code is [`Ok`].
+    ///
+    /// [`Ok`]: StatusCode::Ok
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok)
+    }
+
+    /// Returns `true` if the status code is [`Error`].
+    ///
+    /// [`Error`]: StatusCode::Error
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Error)
+    }
+
+    /// Returns `true` if the status code is [`Cancelled`].
+    ///
+    /// [`Cancelled`]: StatusCode::Cancelled
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Self::Cancelled)
+    }
+}
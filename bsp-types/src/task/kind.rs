@@ -3,7 +3,7 @@ use serde::{
     ser::{Serialize, Serializer},
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum TaskDataKind {
     CompileTask,
     CompileReport,
@@ -12,6 +12,7 @@ pub enum TaskDataKind {
     TestStart,
     TestFinish,
     Custom(String),
+    #[default]
     None,
 }
 
@@ -52,12 +53,6 @@ impl<'de> Deserialize<'de> for TaskDataKind {
     }
 }
 
-impl Default for TaskDataKind {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 impl TaskDataKind {
     pub fn is_none(&self) -> bool {
         matches!(self, Self::None)
@@ -2,10 +2,11 @@ use core::fmt;
 
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
-#[derive(Clone, Serialize_repr, Deserialize_repr)]
+#[derive(Clone, Default, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
 pub enum TaskStatus {
     /// Execution was successful.
+    #[default]
     Ok = 1,
     /// Execution failed.
     Error = 2,
@@ -36,12 +37,6 @@ impl TaskStatus {
     }
 }
 
-impl Default for TaskStatus {
-    fn default() -> Self {
-        Self::Ok
-    }
-}
-
 impl fmt::Debug for TaskStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -2,7 +2,8 @@ use super::BuildTargetCapabilities;
 use super::BuildTargetIdentifier;
 use super::BuildTargetTag;
 use super::Language;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
 
 /// The workspace build targets request is sent from the client to the server to
 /// ask for the list of all available build targets in the workspace.
@@ -38,6 +39,7 @@ pub struct BuildTarget {
     ///   model.
     /// - group together different but related targets in the user interface.
     /// - display icons or colors in the user interface.
+    ///
     /// Pre-defined tags are listed in `BuildTargetTag` but clients and servers
     /// are free to define new tags for custom purposes.
     pub tags: Vec<BuildTargetTag>,
@@ -51,6 +53,21 @@ pub struct BuildTarget {
 
     /// The direct upstream build target dependencies of this build target
     pub dependencies: Vec<BuildTargetIdentifier>,
+
+    /// Kind of data to expect in the `data` field. If this field is not set, the kind of data is
+    /// not specified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_kind: Option<String>,
+
+    /// Language-specific metadata about this target, see [`BuildTargetData`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+/// Language-specific metadata carried by [`BuildTarget::data`].
+pub trait BuildTargetData: Serialize + DeserializeOwned {
+    /// The `dataKind` identifying this metadata.
+    const DATA_KIND: &'static str;
 }
 
 impl BuildTarget {
@@ -71,6 +88,8 @@ impl BuildTarget {
             capabilities,
             language_ids,
             dependencies,
+            data_kind: None,
+            data: None,
         }
     }
     pub fn new_simple(
@@ -88,8 +107,26 @@ impl BuildTarget {
             dependencies,
             display_name: Default::default(),
             base_directory: Default::default(),
+            data_kind: Default::default(),
+            data: Default::default(),
         }
     }
+
+    /// Get the bsp build target's data as `D`, if its data kind is the one of `D`.
+    pub fn typed_data<D: BuildTargetData>(&self) -> Option<serde_json::Result<D>> {
+        if self.data_kind.as_deref() != Some(D::DATA_KIND) {
+            return None;
+        }
+        let data = self.data.clone().unwrap_or_default();
+        Some(serde_json::from_value(data))
+    }
+
+    /// Set the bsp build target's data and data kind from `data`.
+    pub fn set_typed_data<D: BuildTargetData>(&mut self, data: &D) -> serde_json::Result<()> {
+        self.data = Some(serde_json::to_value(data)?);
+        self.data_kind = Some(D::DATA_KIND.to_string());
+        Ok(())
+    }
 }
 
 impl WorkspaceBuildTargetsResult {